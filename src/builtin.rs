@@ -0,0 +1,310 @@
+use num_bigint::BigInt;
+use num_integer::Integer;
+use num_traits::{ToPrimitive, Zero};
+
+use crate::error::CalcError;
+use crate::number::Number;
+use crate::rational::Rational;
+use crate::unit::{Unit, UnitType};
+use crate::value::Value;
+use crate::{number_op, unit, value_op};
+
+/// Functions that are always available, such as `sqrt(x)` or `max(a, b, ...)`.
+/// Like the operators in `value_op`, each knows how to apply itself to values.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Builtin {
+    Sqrt,
+    Cbrt,
+    Abs,
+    Floor,
+    Ceil,
+    Round,
+    Ln,
+    Log10,
+    Log,
+    Exp,
+    Min,
+    Max,
+    Sin,
+    Cos,
+    Tan,
+    Asin,
+    Acos,
+    Atan,
+}
+
+const ALL: [Builtin; 18] = [
+    Builtin::Sqrt,
+    Builtin::Cbrt,
+    Builtin::Abs,
+    Builtin::Floor,
+    Builtin::Ceil,
+    Builtin::Round,
+    Builtin::Ln,
+    Builtin::Log10,
+    Builtin::Log,
+    Builtin::Exp,
+    Builtin::Min,
+    Builtin::Max,
+    Builtin::Sin,
+    Builtin::Cos,
+    Builtin::Tan,
+    Builtin::Asin,
+    Builtin::Acos,
+    Builtin::Atan,
+];
+
+impl Builtin {
+    pub fn from_name(name: &str) -> Option<Builtin> {
+        ALL.into_iter().find(|func| func.name() == name)
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Builtin::Sqrt => "sqrt",
+            Builtin::Cbrt => "cbrt",
+            Builtin::Abs => "abs",
+            Builtin::Floor => "floor",
+            Builtin::Ceil => "ceil",
+            Builtin::Round => "round",
+            Builtin::Ln => "ln",
+            Builtin::Log10 => "log10",
+            Builtin::Log => "log",
+            Builtin::Exp => "exp",
+            Builtin::Min => "min",
+            Builtin::Max => "max",
+            Builtin::Sin => "sin",
+            Builtin::Cos => "cos",
+            Builtin::Tan => "tan",
+            Builtin::Asin => "asin",
+            Builtin::Acos => "acos",
+            Builtin::Atan => "atan",
+        }
+    }
+
+    /// Number of arguments; `None` for `min`/`max`, which take one or more.
+    fn arity(self) -> Option<usize> {
+        match self {
+            Builtin::Min | Builtin::Max => None,
+            Builtin::Log => Some(2),
+            _ => Some(1),
+        }
+    }
+
+    pub fn apply(self, mut args: Vec<Value>) -> Result<Value, CalcError> {
+        let expected = self.arity().unwrap_or(args.len().max(1));
+        if args.len() != expected {
+            return Err(CalcError::ArgumentCount {
+                name: self.name().to_string(),
+                expected,
+                got: args.len(),
+            });
+        }
+        match self {
+            Builtin::Min => extreme(args, false),
+            Builtin::Max => extreme(args, true),
+            Builtin::Log => {
+                let base = unitless(args.pop().expect("two arguments"))?;
+                let val = unitless(args.pop().expect("two arguments"))?;
+                let base_one = number_op::is_zero(&number_op::sub(base.clone(), Number::from(1))?);
+                self.check_domain(is_positive(&val) && is_positive(&base) && !base_one)?;
+                Ok(Value::from(number_op::log(val, base)?))
+            }
+            _ => self.apply_one(args.pop().expect("one argument")),
+        }
+    }
+
+    fn apply_one(self, val: Value) -> Result<Value, CalcError> {
+        // Rounding and `abs` keep the unit: `abs(-3 m)` is `3 m`.
+        let keep_unit = |f: fn(Number) -> Result<Number, CalcError>| {
+            Ok(Value {
+                num: f(val.num.clone())?,
+                unit: val.unit.clone(),
+            })
+        };
+        let num = match self {
+            Builtin::Abs => return keep_unit(|x| Ok(number_op::abs(x))),
+            Builtin::Floor => return keep_unit(number_op::floor),
+            Builtin::Ceil => return keep_unit(number_op::ceil),
+            Builtin::Round => return keep_unit(number_op::round),
+            Builtin::Sqrt => {
+                self.check_domain(!number_op::is_negative(&val.num))?;
+                return root(val, 2);
+            }
+            Builtin::Cbrt => return root(val, 3),
+            Builtin::Ln | Builtin::Log10 => {
+                let val = unitless(val)?;
+                self.check_domain(is_positive(&val))?;
+                let base = match self {
+                    Builtin::Ln => Number::Float(std::f64::consts::E),
+                    _ => Number::from(10),
+                };
+                number_op::log(val, base)?
+            }
+            Builtin::Exp => number_op::float_fn(unitless(val)?, f64::exp, 0, 1),
+            Builtin::Sin | Builtin::Cos | Builtin::Tan => return self.trig(val),
+            Builtin::Asin | Builtin::Acos | Builtin::Atan => return self.inverse_trig(val),
+            Builtin::Log | Builtin::Min | Builtin::Max => unreachable!("not unary"),
+        };
+        Ok(Value::from(num))
+    }
+
+    fn float_trig(self) -> fn(f64) -> f64 {
+        match self {
+            Builtin::Sin => f64::sin,
+            Builtin::Cos => f64::cos,
+            Builtin::Tan => f64::tan,
+            Builtin::Asin => f64::asin,
+            Builtin::Acos => f64::acos,
+            Builtin::Atan => f64::atan,
+            _ => unreachable!("not a trig function"),
+        }
+    }
+
+    // Angles are converted to degrees, where multiples of 30° (45° for `tan`)
+    // give exact results. Unitless arguments are radians.
+    fn trig(self, val: Value) -> Result<Value, CalcError> {
+        let degrees = match &val.unit {
+            None if matches!(&val.num, Number::Int(x) if x.is_zero()) => val.num,
+            None => {
+                return Ok(Value::from(Number::Float(self.float_trig()(
+                    val.num.to_float(),
+                ))))
+            }
+            Some(unit) if unit::get_unit_type(unit) == UnitType::Angle => {
+                unit::convert(val.num, unit, &Unit::AngleDeg)?
+            }
+            Some(_) => return Err(CalcError::OperateWithUnits),
+        };
+        if let Number::Int(d) = &degrees {
+            let d = d.mod_floor(&BigInt::from(360)).to_i64().expect("below 360");
+            let exact = match self {
+                Builtin::Sin => exact_sin(d),
+                Builtin::Cos => exact_sin((d + 90) % 360),
+                _ => match d % 180 {
+                    0 => Some(Rational::from(0)),
+                    45 => Some(Rational::from(1)),
+                    135 => Some(Rational::from(-1)),
+                    90 => return Err(CalcError::DivByZero),
+                    _ => None,
+                },
+            };
+            if let Some(exact) = exact {
+                return Ok(Value::from(Number::from(exact)));
+            }
+        }
+        let radians = degrees.to_float().to_radians();
+        Ok(Value::from(Number::Float(self.float_trig()(radians))))
+    }
+
+    /// Rejects an argument the function isn't defined for, where the float
+    /// maths would print `NaN` or a meaningless number.
+    fn check_domain(self, ok: bool) -> Result<(), CalcError> {
+        if ok {
+            Ok(())
+        } else {
+            Err(CalcError::DomainError(self.name()))
+        }
+    }
+
+    /// The angle in degrees; exact where the sine/tangent is (`asin(1/2)` = 30°).
+    fn inverse_trig(self, val: Value) -> Result<Value, CalcError> {
+        let x = unitless(val)?;
+        if self != Builtin::Atan {
+            let headroom = number_op::sub(Number::from(1), number_op::abs(x.clone()))?;
+            self.check_domain(!number_op::is_negative(&headroom))?;
+        }
+        let table: &[(i64, u64, i64)] = match self {
+            Builtin::Atan => &[(0, 1, 0), (1, 1, 45), (-1, 1, -45)],
+            _ => &[
+                (0, 1, 0),
+                (1, 2, 30),
+                (1, 1, 90),
+                (-1, 2, -30),
+                (-1, 1, -90),
+            ],
+        };
+        let exact = match &x {
+            Number::Float(_) => None,
+            x => {
+                let x = x.clone().to_rational()?;
+                table
+                    .iter()
+                    .find(|(num, den, _)| Rational::new(*num, *den) == x)
+                    .map(|(_, _, degrees)| *degrees)
+            }
+        };
+        let num = match exact {
+            Some(degrees) if self == Builtin::Acos => Number::from(90 - degrees),
+            Some(degrees) => Number::from(degrees),
+            None => Number::Float(self.float_trig()(x.to_float()).to_degrees()),
+        };
+        Ok(Value {
+            num,
+            unit: Some(Unit::AngleDeg),
+        })
+    }
+}
+
+/// `sin(d°)` for `0 <= d < 360`, when it is rational.
+fn exact_sin(d: i64) -> Option<Rational> {
+    match d {
+        0 | 180 => Some(Rational::from(0)),
+        30 | 150 => Some(Rational::new(1, 2)),
+        90 => Some(Rational::from(1)),
+        210 | 330 => Some(Rational::new(-1, 2)),
+        270 => Some(Rational::from(-1)),
+        _ => None,
+    }
+}
+
+fn is_positive(val: &Number) -> bool {
+    !number_op::is_negative(val) && !number_op::is_zero(val)
+}
+
+fn unitless(val: Value) -> Result<Number, CalcError> {
+    match val.unit {
+        Some(_) => Err(CalcError::OperateWithUnits),
+        None => Ok(val.num),
+    }
+}
+
+// The root applies to the unit as well (`sqrt(9 m2)` = `3 m`), which needs
+// every unit power to be divisible by `n`. A named unit that isn't a power of
+// another (`acre`) is taken apart into SI units first: `sqrt(1 acre)` is in m.
+fn root(val: Value, n: u32) -> Result<Value, CalcError> {
+    let Some(unit) = val.unit else {
+        return Ok(Value::from(number_op::root(val.num, n)?));
+    };
+    if let Some(rooted) = unit::root(unit.clone(), n as i32) {
+        return Ok(Value {
+            num: number_op::root(val.num, n)?,
+            unit: Some(rooted),
+        });
+    }
+    let si = unit::si_unit(&unit).ok_or(CalcError::OperateWithUnits)?;
+    let rooted = unit::root(si.clone(), n as i32).ok_or(CalcError::OperateWithUnits)?;
+    Ok(Value {
+        num: number_op::root(unit::convert(val.num, &unit, &si)?, n)?,
+        unit: Some(rooted),
+    })
+}
+
+// Compares through subtraction, so mixed units of one kind work
+// (`max(1 ft, 20 cm)` is `1 ft`). The winner is returned as written.
+fn extreme(args: Vec<Value>, max: bool) -> Result<Value, CalcError> {
+    let mut args = args.into_iter();
+    let mut best = args.next().expect("at least one argument");
+    for arg in args {
+        let diff = value_op::sub(arg.clone(), best.clone())?;
+        let beyond = if max {
+            number_op::is_negative(&number_op::sub_unary(diff.num))
+        } else {
+            number_op::is_negative(&diff.num)
+        };
+        if beyond {
+            best = arg;
+        }
+    }
+    Ok(best)
+}
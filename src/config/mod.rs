@@ -21,6 +21,31 @@ pub struct Config {
     pub format: FormatOptions,
     #[serde(default)]
     pub currency: CurrencyConfig,
+    #[serde(default)]
+    pub units: UnitsConfig,
+}
+
+/// Which variant the bare names of regional volumes (`gallon`, `pint`, `cup`,
+/// `fl oz`, `tbsp`, `tsp`) refer to.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Region {
+    #[default]
+    Us,
+    Imperial,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct UnitsConfig {
+    pub region: Region,
+    /// Pixels per inch, for converting `px` to physical lengths.
+    pub dpi: u32,
+    /// Base font size in pixels, the size of one `em`.
+    pub em: u32,
+    /// User-defined units: name → definition in built-in units (`"1.2 m2"`),
+    /// or an empty string for a base quantity of its own.
+    pub custom: HashMap<String, String>,
 }
 
 #[derive(Debug, Default, PartialEq, Deserialize, Serialize)]
@@ -48,6 +73,9 @@ pub enum NumberRepr {
     Sci,
     Rational,
     Financial,
+    Repeating,
+    /// Angles as degrees-minutes-seconds; other values as `Float`.
+    Dms,
 }
 
 #[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
@@ -96,6 +124,17 @@ impl std::fmt::Display for NumberRepr {
             Self::Sci => "sci",
             Self::Rational => "rational",
             Self::Financial => "financial",
+            Self::Repeating => "repeating",
+            Self::Dms => "dms",
+        })
+    }
+}
+
+impl std::fmt::Display for Region {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Us => "us",
+            Self::Imperial => "imperial",
         })
     }
 }
@@ -142,6 +181,8 @@ pub enum FormatSpec {
     Sci { precision: Option<u8> },
     Rational,
     Financial { precision: Option<u8> },
+    Repeating,
+    Dms,
 }
 
 pub fn apply_spec(base: &FormatOptions, spec: &FormatSpec) -> FormatOptions {
@@ -171,6 +212,12 @@ pub fn apply_spec(base: &FormatOptions, spec: &FormatSpec) -> FormatOptions {
                 opts.fin.precision = *p;
             }
         }
+        FormatSpec::Repeating => {
+            opts.repr = NumberRepr::Repeating;
+        }
+        FormatSpec::Dms => {
+            opts.repr = NumberRepr::Dms;
+        }
     }
     opts
 }
@@ -187,6 +234,17 @@ impl Default for FormatOptions {
     }
 }
 
+impl Default for UnitsConfig {
+    fn default() -> Self {
+        Self {
+            region: Region::Us,
+            dpi: 96,
+            em: 16,
+            custom: HashMap::new(),
+        }
+    }
+}
+
 impl Default for FloatConfig {
     fn default() -> Self {
         Self {
@@ -233,6 +291,14 @@ pub fn current() -> RwLockReadGuard<'static, Config> {
         .expect("config RwLock poisoned")
 }
 
+/// The `[units]` settings. Falls back to the defaults before `init()`, so unit
+/// code stays usable from unit tests.
+pub fn units() -> UnitsConfig {
+    CONFIG.get().map_or_else(UnitsConfig::default, |cfg| {
+        cfg.read().expect("config RwLock poisoned").units.clone()
+    })
+}
+
 fn config_path() -> Result<PathBuf, CalcError> {
     match env::var_os("CALC_CONFIG") {
         Some(p) => Ok(PathBuf::from(p)),
@@ -283,8 +349,20 @@ const DEFAULT_TEMPLATE: &str = "\
 # # [currency.static]
 # # \"EUR/USD\" = 1.08
 
+[units]
+region = \"us\"  # us | imperial: what bare gallon, pint, cup, fl oz, tbsp, tsp mean
+dpi = 96        # pixels per inch, for px
+em = 16         # base font size in px, for em
+
+# # custom units, defined in built-in units; an empty definition is a new base
+# # quantity (e.g. story points)
+# [units.custom]
+# pallet = \"1.2 m2\"
+# sprint = \"2 week\"
+# point = \"\"
+
 [format]
-repr = \"float\"  # fixed | float | sci | rational | financial
+repr = \"float\"  # fixed | float | sci | rational | financial | repeating | dms
 
 [format.float]
 precision         = 4      # decimal places for fixed/float display
@@ -1,4 +1,4 @@
-use super::{Config, CurrencyProvider, NumberRepr};
+use super::{Config, CurrencyProvider, NumberRepr, Region};
 
 /// One settable configuration value: its dotted key path, a getter/setter over
 /// the live `Config`, and the candidate values offered during TAB completion
@@ -17,8 +17,10 @@ fn parse_number_repr(s: &str) -> Result<NumberRepr, String> {
         "sci" => Ok(NumberRepr::Sci),
         "rational" => Ok(NumberRepr::Rational),
         "financial" => Ok(NumberRepr::Financial),
+        "repeating" => Ok(NumberRepr::Repeating),
+        "dms" => Ok(NumberRepr::Dms),
         _ => Err(format!(
-            "expected fixed|float|sci|rational|financial, got {s:?}"
+            "expected fixed|float|sci|rational|financial|repeating|dms, got {s:?}"
         )),
     }
 }
@@ -31,6 +33,14 @@ fn parse_currency_provider(s: &str) -> Result<CurrencyProvider, String> {
     }
 }
 
+fn parse_region(s: &str) -> Result<Region, String> {
+    match s {
+        "us" => Ok(Region::Us),
+        "imperial" => Ok(Region::Imperial),
+        _ => Err(format!("expected us|imperial, got {s:?}")),
+    }
+}
+
 fn parse_bool(s: &str) -> Result<bool, String> {
     match s {
         "true" => Ok(true),
@@ -44,6 +54,13 @@ fn parse_u8(s: &str) -> Result<u8, String> {
         .map_err(|_| format!("expected integer 0–255, got {s:?}"))
 }
 
+fn parse_positive_u32(s: &str) -> Result<u32, String> {
+    s.parse::<u32>()
+        .ok()
+        .filter(|n| *n > 0)
+        .ok_or_else(|| format!("expected a positive integer, got {s:?}"))
+}
+
 fn parse_f64(s: &str) -> Result<f64, String> {
     s.parse::<f64>()
         .map_err(|_| format!("expected a number, got {s:?}"))
@@ -57,7 +74,15 @@ pub static REGISTRY: &[ConfigEntry] = &[
             c.format.repr = parse_number_repr(v)?;
             Ok(())
         },
-        completions: &["fixed", "float", "sci", "rational", "financial"],
+        completions: &[
+            "fixed",
+            "float",
+            "sci",
+            "rational",
+            "financial",
+            "repeating",
+            "dms",
+        ],
     },
     ConfigEntry {
         key: "format.float.precision",
@@ -140,4 +165,31 @@ pub static REGISTRY: &[ConfigEntry] = &[
         },
         completions: &["mnb", "static"],
     },
+    ConfigEntry {
+        key: "units.region",
+        get: |c| c.units.region.to_string(),
+        set: |c, v| {
+            c.units.region = parse_region(v)?;
+            Ok(())
+        },
+        completions: &["us", "imperial"],
+    },
+    ConfigEntry {
+        key: "units.dpi",
+        get: |c| c.units.dpi.to_string(),
+        set: |c, v| {
+            c.units.dpi = parse_positive_u32(v)?;
+            Ok(())
+        },
+        completions: &[],
+    },
+    ConfigEntry {
+        key: "units.em",
+        get: |c| c.units.em.to_string(),
+        set: |c, v| {
+            c.units.em = parse_positive_u32(v)?;
+            Ok(())
+        },
+        completions: &[],
+    },
 ];
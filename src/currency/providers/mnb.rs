@@ -4,6 +4,7 @@ use std::io::BufReader;
 use std::sync::OnceLock;
 
 use chrono::{Days, NaiveDate, Utc};
+use num_bigint::BigInt;
 use quick_xml::de;
 use serde::{Deserialize, Deserializer};
 
@@ -38,14 +39,14 @@ where
     let trimmed = buf.trim_end_matches('0');
     if let Some(idx) = trimmed.rfind(',') {
         let decimals = (trimmed.len() - idx - 1) as u32;
-        let num = trimmed
+        let num: BigInt = trimmed
             .replace(',', "")
             .parse()
             .map_err(serde::de::Error::custom)?;
-        Ok(Rational::new(num, 10_u64.pow(decimals)))
+        Ok(Rational::new_big(num, BigInt::from(10).pow(decimals)))
     } else {
-        let num = trimmed.parse().map_err(serde::de::Error::custom)?;
-        Ok(Rational { num, den: 1 })
+        let num: BigInt = trimmed.parse().map_err(serde::de::Error::custom)?;
+        Ok(Rational::from(num))
     }
 }
 
@@ -139,7 +140,7 @@ impl RateProvider for MnbProvider {
                 let content = fetch_current_rate_xml()?;
                 save_current_rate_xml_file(content.as_str());
                 let rates: MNBCurrentExchangeRates = de::from_str(content.as_str())?;
-                return Ok(to_map(rates.day.rates));
+                Ok(to_map(rates.day.rates))
             })
             .as_ref()?;
 
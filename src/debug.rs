@@ -2,6 +2,7 @@ use std::fmt::{Debug, Formatter};
 
 use crate::node::Node;
 use crate::number::Number;
+use crate::value_op::UnaryOp;
 
 impl Debug for Number {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
@@ -13,6 +14,21 @@ impl Debug for Node {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         match self {
             Node::Value(x) => write!(f, "{}", x),
+            Node::Var(name) => write!(f, "{}", name),
+            Node::Call { name, args, .. } => {
+                let args: Vec<String> = args.iter().map(|arg| format!("{:?}", arg)).collect();
+                write!(f, "{}({})", name, args.join(", "))
+            }
+            Node::Param(idx) => write!(f, "#{}", idx),
+            Node::BadCall { name, .. } => write!(f, "{}(…)", name),
+            Node::Builtin { func, args } => {
+                let args: Vec<String> = args.iter().map(|arg| format!("{:?}", arg)).collect();
+                write!(f, "{}({})", func.name(), args.join(", "))
+            }
+            Node::UnaryExpr {
+                op: UnaryOp::Fact,
+                val,
+            } => write!(f, "({:?}{})", val, UnaryOp::Fact.symbol()),
             Node::UnaryExpr { op, val } => write!(f, "({}{:?})", op.symbol(), val),
             Node::BinaryExpr { op, lhs, rhs } => {
                 write!(f, "({:?}{}{:?})", lhs, op.symbol(), rhs)
@@ -10,8 +10,38 @@ pub enum CalcError {
     DifferentUnitTypes,
     #[error("Cannot operate with units")]
     OperateWithUnits,
+    #[error("Absolute temperatures can only be offset by an interval or subtracted")]
+    AbsoluteTemperature,
     #[error("Conversion error")]
     ConversionError,
+    #[error("Factorial needs a non-negative integer")]
+    InvalidFactorial,
+    #[error("Argument outside the domain of {0}")]
+    DomainError(&'static str),
+    #[error("Unknown variable: {0}")]
+    UnknownVariable(String),
+    #[error("Cannot assign to {0}: it is a unit or keyword")]
+    ReservedName(String),
+    #[error("Unknown function: {0}")]
+    UnknownFunction(String),
+    #[error("{name} takes {expected} argument(s), got {got}")]
+    ArgumentCount {
+        name: String,
+        expected: usize,
+        got: usize,
+    },
+    #[error("Duplicate parameter {0}")]
+    DuplicateParameter(String),
+    #[error("Parameter {0} is used as a unit in the body")]
+    ShadowedUnit(String),
+    #[error("Cannot redefine built-in function {0}")]
+    BuiltinFunction(String),
+    #[error("Function {0} would call itself")]
+    RecursiveFunction(String),
+    #[error("Cannot use {0} as an exact number")]
+    NotFinite(f64),
+    #[error("Number out of range: {0}")]
+    NumberOutOfRange(String),
     #[error("Missing unit")]
     MissingUnit,
     #[error("Could not determine a cache directory (no home directory)")]
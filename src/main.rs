@@ -2,6 +2,7 @@ use std::env;
 
 use crate::error::CalcError;
 
+mod builtin;
 mod config;
 mod currency;
 mod debug;
@@ -19,6 +20,7 @@ mod value_op;
 
 fn main() -> Result<(), CalcError> {
     config::init()?;
+    repl::init_custom_units()?;
     let args: Vec<String> = env::args().collect();
     if args.len() <= 1 {
         repl::run()
@@ -1,12 +1,48 @@
+use std::rc::Rc;
+
+use crate::builtin::Builtin;
 use crate::error::CalcError;
 use crate::number::Number;
+use crate::parser::parser::Function;
 use crate::unit::Unit;
 use crate::value::Value;
 use crate::value_op::{BinaryOp, UnaryOp};
 
+/// One complete input line: an expression to print, `name = expression`, or a
+/// function definition `name(params) = expression`.
+pub enum Statement {
+    Expr(Node),
+    Assign(String, Node),
+    Define(String, Function),
+    /// Well-formed but not executable, e.g. assigning to a unit name (`m = 5`).
+    Invalid(CalcError),
+}
+
 #[derive(Clone)]
 pub enum Node {
     Value(Value),
+    /// A name that wasn't a variable when the line was parsed.
+    Var(String),
+    /// A call to a user-defined function. The body is parsed once per line and
+    /// shared between calls; its `Param`s are bound to `args` at eval time.
+    Call {
+        name: String,
+        body: Rc<Node>,
+        args: Vec<Node>,
+    },
+    /// The `n`-th argument of the function whose body this is.
+    Param(usize),
+    /// A call that couldn't be parsed into a `Call`: the function is unknown
+    /// (`expected` is `None`) or got the wrong number of arguments.
+    BadCall {
+        name: String,
+        args: usize,
+        expected: Option<usize>,
+    },
+    Builtin {
+        func: Builtin,
+        args: Vec<Node>,
+    },
     UnaryExpr {
         op: UnaryOp,
         val: Box<Node>,
@@ -20,14 +56,45 @@ pub enum Node {
 
 impl Node {
     pub fn value(num: Number, unit: Option<Unit>) -> Node {
-        return Node::Value(Value { num, unit });
+        Node::Value(Value { num, unit })
+    }
+
+    pub fn eval(&self) -> Result<Value, CalcError> {
+        self.eval_with(&[])
     }
 
-    pub fn eval(self) -> Result<Value, CalcError> {
+    /// Evaluates with `params` as the arguments of the enclosing function call.
+    fn eval_with(&self, params: &[Value]) -> Result<Value, CalcError> {
+        let eval_all = |nodes: &[Node]| {
+            nodes
+                .iter()
+                .map(|node| node.eval_with(params))
+                .collect::<Result<Vec<_>, _>>()
+        };
         match self {
-            Node::Value(val) => Ok(val),
-            Node::UnaryExpr { op, val } => op.apply(val.eval()?),
-            Node::BinaryExpr { op, lhs, rhs } => op.apply(lhs.eval()?, rhs.eval()?),
+            Node::Value(val) => Ok(val.clone()),
+            Node::Var(name) => Err(CalcError::UnknownVariable(name.clone())),
+            Node::Call { body, args, .. } => body.eval_with(&eval_all(args)?),
+            Node::Param(idx) => Ok(params[*idx].clone()),
+            Node::BadCall {
+                name,
+                expected: None,
+                ..
+            } => Err(CalcError::UnknownFunction(name.clone())),
+            Node::BadCall {
+                name,
+                args,
+                expected: Some(expected),
+            } => Err(CalcError::ArgumentCount {
+                name: name.clone(),
+                expected: *expected,
+                got: *args,
+            }),
+            Node::Builtin { func, args } => func.apply(eval_all(args)?),
+            Node::UnaryExpr { op, val } => op.apply(val.eval_with(params)?),
+            Node::BinaryExpr { op, lhs, rhs } => {
+                op.apply(lhs.eval_with(params)?, rhs.eval_with(params)?)
+            }
         }
     }
 }
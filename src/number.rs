@@ -1,36 +1,36 @@
+use std::collections::HashMap;
 use std::fmt::{Display, Formatter};
 
+use num_bigint::BigInt;
+use num_integer::Integer;
+use num_traits::{FromPrimitive, Signed, ToPrimitive, Zero};
+
 use crate::config::{self, FormatOptions, NumberRepr};
+use crate::error::CalcError;
 use crate::rational::Rational;
 
 #[derive(Clone, PartialEq)]
 pub enum Number {
-    Int(i64),
+    Int(BigInt),
     Rational(Rational),
     Float(f64),
 }
 
 pub fn format_number(num: &Number, opts: &FormatOptions) -> String {
     match num {
-        Number::Int(x) => format_int(*x, opts),
-        Number::Rational(r) => match opts.repr {
-            NumberRepr::Rational => format!("{}/{}", r.num, r.den),
-            _ => {
-                let f: f64 = r.into();
-                format_float(f, opts)
-            }
-        },
+        Number::Int(x) => format_int(x, opts),
+        Number::Rational(r) => format_rational(r, opts),
         Number::Float(x) => format_float(*x, opts),
     }
 }
 
-fn format_int(x: i64, opts: &FormatOptions) -> String {
+fn format_int(x: &BigInt, opts: &FormatOptions) -> String {
     match opts.repr {
-        NumberRepr::Financial => format_financial(x as f64, opts.fin.precision),
-        NumberRepr::Sci => format_scientific(x as f64, opts.sci.precision),
+        NumberRepr::Financial => format_exact_financial(&x.clone().into(), opts.fin.precision),
+        NumberRepr::Sci => format_exact_scientific(&x.clone().into(), opts.sci.precision),
         _ => {
-            if opts.int.sci_upgrade && (x.unsigned_abs() as f64) >= opts.int.sci_upgrade_upper {
-                format_scientific(x as f64, opts.sci.precision)
+            if opts.int.sci_upgrade && int_to_f64(&x.abs()) >= opts.int.sci_upgrade_upper {
+                format_exact_scientific(&x.clone().into(), opts.sci.precision)
             } else {
                 format!("{}", x)
             }
@@ -38,16 +38,43 @@ fn format_int(x: i64, opts: &FormatOptions) -> String {
     }
 }
 
+// Rationals are expanded by exact long division, so any precision is honoured
+// without the float noise past ~16 digits.
+fn format_rational(r: &Rational, opts: &FormatOptions) -> String {
+    match opts.repr {
+        NumberRepr::Rational => format!("{}/{}", r.num, r.den),
+        NumberRepr::Repeating => format_repeating(r, opts.float.precision),
+        NumberRepr::Fixed => format_exact_fixed(r, opts.float.precision),
+        NumberRepr::Float | NumberRepr::Dms => {
+            let abs = f64::from(r).abs();
+            if opts.float.sci_upgrade
+                && (abs >= opts.float.sci_upgrade_upper
+                    || (abs > 0.0 && abs < opts.float.sci_upgrade_lower))
+            {
+                format_exact_scientific(r, opts.sci.precision)
+            } else {
+                format_exact_fixed(r, opts.float.precision)
+            }
+        }
+        NumberRepr::Sci => format_exact_scientific(r, opts.sci.precision),
+        NumberRepr::Financial => format_exact_financial(r, opts.fin.precision),
+    }
+}
+
 fn format_float(x: f64, opts: &FormatOptions) -> String {
     if !x.is_finite() {
         return format!("{}", x);
     }
-    if x.fract() == 0.0 && x.abs() <= i64::MAX as f64 {
-        return format_int(x as i64, opts);
+    // A whole float prints as an integer unless it is large enough to upgrade
+    // to sci; past that its trailing digits are float noise (`1 pc to m`).
+    let upgrade = opts.float.sci_upgrade && x.abs() >= opts.float.sci_upgrade_upper;
+    if x.fract() == 0.0 && x.abs() <= i64::MAX as f64 && !upgrade {
+        let int = BigInt::from_f64(x).expect("finite whole float");
+        return format_int(&int, opts);
     }
     match opts.repr {
         NumberRepr::Fixed | NumberRepr::Rational => format_fixed(x, opts.float.precision),
-        NumberRepr::Float => {
+        NumberRepr::Float | NumberRepr::Repeating | NumberRepr::Dms => {
             let abs = x.abs();
             if opts.float.sci_upgrade
                 && (abs >= opts.float.sci_upgrade_upper
@@ -109,6 +136,184 @@ fn format_scientific(x: f64, precision: u8) -> String {
     format!("{}e{}", mant_out, exp_str)
 }
 
+pub fn int_to_f64(x: &BigInt) -> f64 {
+    x.to_f64().unwrap_or(if x.is_negative() {
+        f64::NEG_INFINITY
+    } else {
+        f64::INFINITY
+    })
+}
+
+/// `round(|r| * 10^precision)` (half away from zero), and whether that was exact.
+fn scaled_digits(r: &Rational, precision: usize) -> (BigInt, bool) {
+    let scaled = r.num.abs() * BigInt::from(10).pow(precision as u32);
+    let (quot, rem) = scaled.div_rem(&r.den);
+    let exact = rem.is_zero();
+    if rem * 2 >= r.den {
+        (quot + 1, exact)
+    } else {
+        (quot, exact)
+    }
+}
+
+/// Inserts the decimal point `precision` digits from the right.
+fn place_point(digits: &BigInt, precision: usize) -> String {
+    let digits = format!("{:0>width$}", digits.to_string(), width = precision + 1);
+    if precision == 0 {
+        digits
+    } else {
+        let (int, frac) = digits.split_at(digits.len() - precision);
+        format!("{}.{}", int, frac)
+    }
+}
+
+fn sign(r: &Rational) -> &'static str {
+    if r.num.is_negative() {
+        "-"
+    } else {
+        ""
+    }
+}
+
+fn format_exact_fixed(r: &Rational, precision: u8) -> String {
+    let p = precision as usize;
+    let (digits, exact) = scaled_digits(r, p);
+    let out = format!("{}{}", sign(r), place_point(&digits, p));
+    if exact {
+        trim_zeros(out)
+    } else {
+        format!("{}\u{2026}", out)
+    }
+}
+
+fn format_exact_financial(r: &Rational, precision: u8) -> String {
+    let p = precision as usize;
+    let million = Rational::from(1_000_000);
+    let (r, suffix) = if r.num.abs() >= r.den.clone() * 1_000_000 {
+        (r / &million, "m")
+    } else {
+        (r.clone(), "")
+    };
+    let (digits, exact) = scaled_digits(&r, p);
+    let ellipsis = if exact { "" } else { "\u{2026}" };
+    format!(
+        "{}{}{}{}",
+        sign(&r),
+        place_point(&digits, p),
+        ellipsis,
+        suffix
+    )
+}
+
+// Scientific notation from the exact value, so big integers and long fractions
+// keep their true leading digits instead of f64's ~16.
+fn format_exact_scientific(r: &Rational, precision: u8) -> String {
+    if r.num.is_zero() {
+        return "0e0".to_string();
+    }
+    let p = precision as usize;
+    let ten = BigInt::from(10);
+    let abs = r.num.abs();
+    // First guess from the digit counts, then correct so 1 <= mantissa < 10.
+    let mut exp = abs.to_string().len() as i64 - r.den.to_string().len() as i64;
+    let below = |exp: i64| {
+        if exp >= 0 {
+            abs < &r.den * ten.pow(exp as u32)
+        } else {
+            &abs * ten.pow(exp.unsigned_abs() as u32) < r.den
+        }
+    };
+    if below(exp) {
+        exp -= 1;
+    }
+    let mantissa = if exp >= 0 {
+        Rational::new_big(abs.clone(), &r.den * ten.pow(exp as u32))
+    } else {
+        Rational::new_big(&abs * ten.pow(exp.unsigned_abs() as u32), r.den.clone())
+    };
+    let (mut digits, exact) = scaled_digits(&mantissa, p);
+    if digits >= ten.pow(p as u32 + 1) {
+        // Rounding carried into a new digit, e.g. 9.99… -> 10.0
+        digits /= 10;
+        exp += 1;
+    }
+    let mant = place_point(&digits, p);
+    if exact {
+        format!("{}{}e{}", sign(r), trim_zeros(mant), exp)
+    } else {
+        format!("{}{}\u{2026}e{}", sign(r), mant, exp)
+    }
+}
+
+/// Longest pre-period plus period the repeating notation will spell out
+/// before falling back to a rounded fixed expansion.
+const MAX_REPEATING_DIGITS: usize = 100;
+
+// Long division remembering each remainder: the first repeated remainder marks
+// the start of the period, `1/6` -> `0.1(6)`.
+fn format_repeating(r: &Rational, precision: u8) -> String {
+    let (int, mut rem) = r.num.abs().div_rem(&r.den);
+    let mut digits = String::new();
+    let mut seen: HashMap<BigInt, usize> = HashMap::new();
+    while !rem.is_zero() {
+        if let Some(&start) = seen.get(&rem) {
+            let (pre, period) = digits.split_at(start);
+            return format!("{}{}.{}({})", sign(r), int, pre, period);
+        }
+        if digits.len() >= MAX_REPEATING_DIGITS {
+            return format_exact_fixed(r, precision);
+        }
+        seen.insert(rem.clone(), digits.len());
+        rem *= 10;
+        let (digit, next) = rem.div_rem(&r.den);
+        digits.push_str(&digit.to_string());
+        rem = next;
+    }
+    format!("{}{}.{}", sign(r), int, digits)
+}
+
+/// Degrees as `12°34'56"`; the seconds are formatted like any other number.
+pub fn format_dms(degrees: &Number, opts: &FormatOptions) -> String {
+    // Only `inf` and `NaN` have no exact value; they print as plain degrees.
+    let r = match degrees.clone().to_rational() {
+        Ok(r) => r,
+        Err(_) => return format!("{}°", degrees.clone().to_float()),
+    };
+    let sixty = Rational::from(60);
+    let abs = Rational::new_big(r.num.abs(), r.den.clone());
+    let mut deg = abs.num.div_floor(&abs.den);
+    let rest = &(&abs - &Rational::from(deg.clone())) * &sixty;
+    let mut min = rest.num.div_floor(&rest.den);
+    let mut sec = &(&rest - &Rational::from(min.clone())) * &sixty;
+    // Seconds that round up to 60 at the display precision carry into the
+    // minutes, and 60 minutes into the degrees, so 59'59.99999" reads 1°0'0".
+    let scale = Rational::new_big(
+        BigInt::from(10).pow(u32::from(opts.float.precision)),
+        BigInt::from(1),
+    );
+    let half = Rational::new(1, 2);
+    let shown = &(&sec * &scale) + &half;
+    if shown.num.div_floor(&shown.den) >= BigInt::from(60) * &scale.num {
+        sec = Rational::from(0);
+        min += 1;
+        if min == BigInt::from(60) {
+            min = BigInt::from(0);
+            deg += 1;
+        }
+    }
+    let sec_opts = FormatOptions {
+        repr: NumberRepr::Float,
+        ..opts.clone()
+    };
+    format!(
+        "{}{}°{}'{}\"",
+        sign(&r),
+        deg,
+        min,
+        format_number(&sec.into(), &sec_opts)
+    )
+}
+
 fn trim_zeros(s: String) -> String {
     if s.contains('.') {
         s.trim_end_matches('0').trim_end_matches('.').to_string()
@@ -125,30 +330,48 @@ impl Display for Number {
 
 impl From<i64> for Number {
     fn from(item: i64) -> Self {
+        Number::Int(item.into())
+    }
+}
+
+impl From<BigInt> for Number {
+    fn from(item: BigInt) -> Self {
         Number::Int(item)
     }
 }
 
+impl From<Rational> for Number {
+    /// Normalizes whole-valued fractions to `Int`.
+    fn from(item: Rational) -> Self {
+        if item.is_integer() {
+            Number::Int(item.num)
+        } else {
+            Number::Rational(item)
+        }
+    }
+}
+
 impl From<f64> for Number {
     fn from(item: f64) -> Self {
         Number::Float(item)
     }
 }
 
+#[allow(clippy::wrong_self_convention)]
 impl Number {
     pub fn to_float(self) -> f64 {
         match self {
-            Number::Int(x) => x as f64,
+            Number::Int(x) => int_to_f64(&x),
             Number::Rational(rat) => rat.into(),
             Number::Float(x) => x,
         }
     }
 
-    pub fn to_rational(self) -> Rational {
+    pub fn to_rational(self) -> Result<Rational, CalcError> {
         match self {
-            Number::Int(x) => x.into(),
-            Number::Rational(rat) => rat,
-            Number::Float(x) => panic!("Cannot cast float {x} to rational"),
+            Number::Int(x) => Ok(x.into()),
+            Number::Rational(rat) => Ok(rat),
+            Number::Float(x) => Rational::from_f64(x).ok_or(CalcError::NotFinite(x)),
         }
     }
 }
@@ -1,64 +1,83 @@
+use num_bigint::BigInt;
+use num_integer::Integer;
+use num_traits::{One, Signed, ToPrimitive, Zero};
+
 use crate::error::CalcError;
-use crate::number::Number;
+use crate::number::{int_to_f64, Number};
 use crate::rational::Rational;
 
+/// Exact results larger than this many bits fall back to `Float` instead of
+/// allocating (and printing) an enormous integer.
+const MAX_EXACT_BITS: u64 = 1 << 20;
+/// Largest exact factorial (about 250k bits); beyond it `n!` is `inf` like any
+/// other overflowing float.
+const MAX_EXACT_FACTORIAL: u32 = 20_000;
+
 pub fn pow(lhs: Number, rhs: Number) -> Result<Number, CalcError> {
     match (lhs, rhs) {
-        (Number::Int(lhs), Number::Int(rhs)) => {
-            if let Some(exp) = u32::try_from(rhs.unsigned_abs()).ok() {
-                if let Some(res) = lhs.checked_pow(exp) {
-                    return if rhs >= 0 {
-                        Ok(Number::Int(res))
-                    } else {
-                        Ok(Number::Rational(Rational::inverse(res)?))
-                    };
-                }
-            }
-            if let Ok(rhs) = i32::try_from(rhs) {
-                Ok(Number::Float((lhs as f64).powi(rhs)))
-            } else {
-                Ok(Number::Float((lhs as f64).powf(rhs as f64)))
-            }
-        }
-        (Number::Float(lhs), Number::Int(rhs)) => {
-            if let Ok(rhs) = i32::try_from(rhs) {
-                Ok(Number::Float(lhs.powi(rhs)))
-            } else {
-                Ok(Number::Float(lhs.powf(rhs as f64)))
+        (Number::Float(lhs), Number::Int(rhs)) => match rhs.to_i32() {
+            Some(rhs) => Ok(Number::Float(lhs.powi(rhs))),
+            None => Ok(Number::Float(lhs.powf(int_to_f64(&rhs)))),
+        },
+        (lhs, Number::Float(rhs)) => Ok(Number::Float(lhs.to_float().powf(rhs))),
+        (Number::Float(lhs), rhs) => Ok(Number::Float(lhs.powf(rhs.to_float()))),
+        (lhs, Number::Int(rhs)) => {
+            let base = lhs.to_rational()?;
+            match exact_exponent(&base, &rhs) {
+                Some(exp) => Ok(base.checked_pow(exp)?.into()),
+                None => Ok(Number::Float(f64::from(base).powf(int_to_f64(&rhs)))),
             }
         }
-        (lhs, Number::Float(rhs)) => Ok(Number::Float(lhs.to_float().powf(rhs))),
-        (Number::Rational(lhs), Number::Int(rhs)) => {
-            if let Ok(rhs) = i32::try_from(rhs) {
-                if let Some(res) = lhs.checked_pow(rhs) {
-                    Ok(Number::Rational(res))
-                } else {
-                    let lhs: f64 = lhs.into();
-                    Ok(Number::Float(lhs.powi(rhs)))
+        (lhs, Number::Rational(rhs)) => {
+            // p/q-th power: exact when the base is a perfect q-th power.
+            let base = lhs.to_rational()?;
+            if let Some(q) = rhs.den.to_u32() {
+                if let Some(root) = base.root(q) {
+                    return pow(root.into(), Number::Int(rhs.num));
                 }
-            } else {
-                let lhs: f64 = lhs.into();
-                Ok(Number::Float(lhs.powf(rhs as f64)))
             }
+            Ok(Number::Float(f64::from(base).powf(f64::from(rhs))))
         }
-        (lhs, rhs) => Ok(Number::Float(lhs.to_float().powf(rhs.to_float()))),
     }
 }
 
-pub fn mul(lhs: Number, rhs: Number) -> Number {
-    match (lhs, rhs) {
+/// The exponent as `i32` if `base^exp` stays within `MAX_EXACT_BITS`.
+fn exact_exponent(base: &Rational, exp: &BigInt) -> Option<i32> {
+    if base.num.abs().is_one() && base.den.is_one() {
+        // ±1 to any power; only the parity matters.
+        return Some(if exp.bit(0) { 1 } else { 0 });
+    }
+    let exp = exp.to_i32()?;
+    let bits = base.num.bits().max(base.den.bits());
+    if bits.saturating_mul(exp.unsigned_abs() as u64) > MAX_EXACT_BITS {
+        return None;
+    }
+    Some(exp)
+}
+
+pub fn factorial(val: Number) -> Result<Number, CalcError> {
+    let n = match val {
+        Number::Int(n) => n,
+        Number::Rational(r) if r.is_integer() => r.num,
+        Number::Float(x) if x.fract() == 0.0 => BigInt::from(x as i64),
+        _ => return Err(CalcError::InvalidFactorial),
+    };
+    if n.is_negative() {
+        return Err(CalcError::InvalidFactorial);
+    }
+    match n.to_u32() {
+        Some(n) if n <= MAX_EXACT_FACTORIAL => Ok(Number::Int((2..=n).map(BigInt::from).product())),
+        _ => Ok(Number::Float(f64::INFINITY)),
+    }
+}
+
+pub fn mul(lhs: Number, rhs: Number) -> Result<Number, CalcError> {
+    Ok(match (lhs, rhs) {
         (Number::Int(lhs), Number::Int(rhs)) => Number::Int(lhs * rhs),
         (Number::Float(lhs), rhs) => Number::Float(lhs * rhs.to_float()),
         (lhs, Number::Float(rhs)) => Number::Float(lhs.to_float() * rhs),
-        (lhs, rhs) => {
-            let res: Rational = lhs.to_rational() * rhs.to_rational();
-            if res.den == 1 {
-                Number::Int(res.num)
-            } else {
-                Number::Rational(res)
-            }
-        }
-    }
+        (lhs, rhs) => (lhs.to_rational()? * rhs.to_rational()?).into(),
+    })
 }
 
 pub fn div(lhs: Number, rhs: Number) -> Result<Number, CalcError> {
@@ -76,52 +95,52 @@ pub fn div(lhs: Number, rhs: Number) -> Result<Number, CalcError> {
             }
             Ok(Number::Float(lhs.to_float() / rhs))
         }
+        (lhs, rhs) => Ok(lhs.to_rational()?.checked_div(&rhs.to_rational()?)?.into()),
+    }
+}
+
+/// Floor division: rounds toward negative infinity, so `-7 // 2` is `-4`.
+pub fn int_div(lhs: Number, rhs: Number) -> Result<Number, CalcError> {
+    match (lhs, rhs) {
+        (Number::Float(lhs), rhs) => float_int_div(lhs, rhs.to_float()),
+        (lhs, Number::Float(rhs)) => float_int_div(lhs.to_float(), rhs),
         (lhs, rhs) => {
-            let rhs = rhs.to_rational();
-            if rhs == 0 {
-                return Err(CalcError::DivByZero);
-            }
-            let lhs = lhs.to_rational();
-            let res = lhs / rhs;
-            if res.den == 1 {
-                Ok(Number::Int(res.num))
-            } else {
-                Ok(Number::Rational(res))
-            }
+            let quot = lhs.to_rational()?.checked_div(&rhs.to_rational()?)?;
+            Ok(Number::Int(quot.num.div_floor(&quot.den)))
         }
     }
 }
 
-pub fn add(lhs: Number, rhs: Number) -> Number {
-    match (lhs, rhs) {
+fn float_int_div(lhs: f64, rhs: f64) -> Result<Number, CalcError> {
+    if rhs == 0.0 {
+        return Err(CalcError::DivByZero);
+    }
+    Ok(Number::Float((lhs / rhs).floor()))
+}
+
+/// Remainder of floor division, `lhs - rhs * (lhs // rhs)`; takes the sign of
+/// the divisor, so `-7 % 3` is `2`.
+pub fn modulo(lhs: Number, rhs: Number) -> Result<Number, CalcError> {
+    let quot = int_div(lhs.clone(), rhs.clone())?;
+    sub(lhs, mul(rhs, quot)?)
+}
+
+pub fn add(lhs: Number, rhs: Number) -> Result<Number, CalcError> {
+    Ok(match (lhs, rhs) {
         (Number::Int(lhs), Number::Int(rhs)) => Number::Int(lhs + rhs),
         (Number::Float(lhs), rhs) => Number::Float(lhs + rhs.to_float()),
         (lhs, Number::Float(rhs)) => Number::Float(lhs.to_float() + rhs),
-        (lhs, rhs) => {
-            let res: Rational = lhs.to_rational() + rhs.to_rational();
-            if res.den == 1 {
-                Number::Int(res.num)
-            } else {
-                Number::Rational(res)
-            }
-        }
-    }
+        (lhs, rhs) => (lhs.to_rational()? + rhs.to_rational()?).into(),
+    })
 }
 
-pub fn sub(lhs: Number, rhs: Number) -> Number {
-    match (lhs, rhs) {
+pub fn sub(lhs: Number, rhs: Number) -> Result<Number, CalcError> {
+    Ok(match (lhs, rhs) {
         (Number::Int(lhs), Number::Int(rhs)) => Number::Int(lhs - rhs),
         (Number::Float(lhs), rhs) => Number::Float(lhs - rhs.to_float()),
         (lhs, Number::Float(rhs)) => Number::Float(lhs.to_float() - rhs),
-        (lhs, rhs) => {
-            let res: Rational = lhs.to_rational() - rhs.to_rational();
-            if res.den == 1 {
-                Number::Int(res.num)
-            } else {
-                Number::Rational(res)
-            }
-        }
-    }
+        (lhs, rhs) => (lhs.to_rational()? - rhs.to_rational()?).into(),
+    })
 }
 
 pub fn sub_unary(val: Number) -> Number {
@@ -131,3 +150,108 @@ pub fn sub_unary(val: Number) -> Number {
         Number::Rational(val) => Number::Rational(-val),
     }
 }
+
+pub fn abs(val: Number) -> Number {
+    match val {
+        Number::Int(val) => Number::Int(val.abs()),
+        Number::Float(val) => Number::Float(val.abs()),
+        Number::Rational(val) => Number::Rational(Rational::new_big(val.num.abs(), val.den)),
+    }
+}
+
+pub fn floor(val: Number) -> Result<Number, CalcError> {
+    Ok(match val {
+        Number::Float(val) => Number::Float(val.floor()),
+        val => {
+            let val = val.to_rational()?;
+            Number::Int(val.num.div_floor(&val.den))
+        }
+    })
+}
+
+pub fn ceil(val: Number) -> Result<Number, CalcError> {
+    Ok(match val {
+        Number::Float(val) => Number::Float(val.ceil()),
+        val => {
+            let val = val.to_rational()?;
+            Number::Int(val.num.div_ceil(&val.den))
+        }
+    })
+}
+
+/// Rounds half away from zero, so `round(2.5)` is 3 and `round(-2.5)` is -3.
+pub fn round(val: Number) -> Result<Number, CalcError> {
+    Ok(match val {
+        Number::Float(val) => Number::Float(val.round()),
+        val => {
+            let val = val.to_rational()?;
+            let shifted: BigInt = val.num.abs() * 2 + &val.den;
+            let half_up = shifted.div_floor(&(&val.den * 2));
+            Number::Int(if val.num.is_negative() {
+                -half_up
+            } else {
+                half_up
+            })
+        }
+    })
+}
+
+/// `n`-th root; exact for perfect powers (`sqrt(9/4)` = `3/2`), else `Float`.
+pub fn root(val: Number, n: u32) -> Result<Number, CalcError> {
+    pow(val, Number::Rational(Rational::new(1, n as u64)))
+}
+
+/// Logarithm of `val` to `base`. Exact when `val` is an integer power of an
+/// exact base (`log(8, 2)` = 3, `log10(1/1000)` = -3), else `Float`.
+pub fn log(val: Number, base: Number) -> Result<Number, CalcError> {
+    let (x, b) = (val.clone().to_float(), base.clone().to_float());
+    let approx = if b == 10.0 {
+        x.log10()
+    } else {
+        x.ln() / b.ln()
+    };
+    if let (Number::Int(_) | Number::Rational(_), Number::Int(_) | Number::Rational(_)) =
+        (&val, &base)
+    {
+        if approx.is_finite() && approx.abs() < MAX_EXACT_BITS as f64 {
+            let exp = approx.round() as i32;
+            let base = base.to_rational()?;
+            if exact_exponent(&base, &BigInt::from(exp)) == Some(exp)
+                && base.checked_pow(exp)? == val.to_rational()?
+            {
+                return Ok(Number::Int(exp.into()));
+            }
+        }
+    }
+    Ok(Number::Float(approx))
+}
+
+/// Applies a float-only function, keeping the trivially exact point
+/// `f(at) = exact` (e.g. `cos(0)` = 1) exact.
+pub fn float_fn(val: Number, f: fn(f64) -> f64, at: i64, exact: i64) -> Number {
+    let is_exact_point = match &val {
+        Number::Int(x) => *x == BigInt::from(at),
+        _ => false,
+    };
+    if is_exact_point {
+        Number::from(exact)
+    } else {
+        Number::Float(f(val.to_float()))
+    }
+}
+
+pub fn is_negative(val: &Number) -> bool {
+    match val {
+        Number::Int(x) => x.is_negative(),
+        Number::Rational(x) => x.num.is_negative(),
+        Number::Float(x) => *x < 0.0,
+    }
+}
+
+pub fn is_zero(val: &Number) -> bool {
+    match val {
+        Number::Int(x) => x.is_zero(),
+        Number::Rational(x) => x.num.is_zero(),
+        Number::Float(x) => *x == 0.0,
+    }
+}
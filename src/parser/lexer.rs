@@ -1,36 +1,126 @@
+use num_bigint::BigInt;
 use regex::{Captures, Regex};
 use strum::EnumCount;
 
 use super::token::{Token, CURRENCIES_PATTERN};
+use crate::rational::Rational;
+use crate::unit::Prefix;
 
 pub struct Lexer {
     patterns: Regex,
+    ident: Regex,
 }
 
-static PATTERNS: [(&'static str, fn(&str) -> Token); Token::COUNT] = [
+type Pattern = (&'static str, fn(&str) -> Token);
+
+const IDENT_PATTERN: &str = "[A-Za-z_][A-Za-z0-9_]*";
+
+// Decimal literals are exact; exponents too large to expand (`1e99999`) are rejected.
+fn decimal(x: &str, scale: i64) -> Token {
+    match x.parse::<Rational>() {
+        Ok(r) => Token::LitDec(r * Rational::from(scale)),
+        Err(()) => Token::INVALID(x.to_string()),
+    }
+}
+
+// Degrees-minutes-seconds such as `12°34'56"`. The `'` and `"` here are arc
+// minutes and seconds, not feet and inches, so the whole literal is one token.
+const DMS_PATTERN: &str =
+    r#"[0-9]+(?:\.[0-9]+)?°(?:\s*[0-9]+(?:\.[0-9]+)?'(?:\s*[0-9]+(?:\.[0-9]+)?")?)?"#;
+
+fn dms(x: &str) -> Token {
+    let mut degrees = Rational::from(0);
+    for (part, scale) in x.split_inclusive(['°', '\'', '"']).zip([1, 60, 3600]) {
+        let digits = part.trim().trim_end_matches(['°', '\'', '"']);
+        match digits.parse::<Rational>() {
+            Ok(r) => degrees = degrees + r / Rational::from(scale),
+            Err(()) => return Token::INVALID(x.to_string()),
+        }
+    }
+    Token::LitDms(degrees)
+}
+
+// An SI prefix followed by a metric base unit, e.g. `km`, `µs`, `dl`, `mL` or
+// `kWh`. `F`, `C` and `K` take every prefix but `d`, since `dF`, `dC` and `dK`
+// are the temperature intervals.
+const PREFIXED_PATTERN: &str =
+    "[pnµμumcdhkMGT](?:m2|m3|m|g|l|L|s|J|Wh|W|cal|Pa|bar|pc|Hz|H|V|A|Ω|ohms|ohm)|[pnµμumchkMGT][FCK]";
+
+fn prefixed(x: &str) -> Token {
+    let split = x.chars().next().map_or(0, char::len_utf8);
+    let (prefix, base) = x.split_at(split);
+    let prefix = Prefix::from_symbol(prefix);
+    let base = match base {
+        "m" => Token::LenM,
+        "m2" => Token::AreaM,
+        "m3" => Token::VolM,
+        "g" => Token::MassG,
+        "l" | "L" => Token::VolLiter,
+        "s" => Token::TimeSec,
+        "J" => Token::EnergyJoule,
+        "Wh" => Token::EnergyWh,
+        "W" => Token::PowerWatt,
+        "cal" => Token::EnergyCal,
+        "Pa" => Token::PressurePa,
+        "bar" => Token::PressureBar,
+        "pc" => Token::LenParsec,
+        "Hz" => Token::FreqHz,
+        "H" => Token::ElecHenry,
+        "V" => Token::ElecVolt,
+        "A" => Token::ElecAmpere,
+        "Ω" | "ohms" | "ohm" => Token::ElecOhm,
+        "F" => Token::ElecFarad,
+        "C" => Token::ElecCoulomb,
+        "K" => Token::TempK,
+        _ => return Token::INVALID(x.to_string()),
+    };
+    match prefix {
+        Some(prefix) => Token::Prefixed(prefix, Box::new(base)),
+        None => Token::INVALID(x.to_string()),
+    }
+}
+
+// Data units take their own set of prefixes: decimal `kB`, `Mbit` and binary
+// `KiB`, `GiB`. Metric-only prefixes such as `m` don't apply.
+fn data(x: &str, suffixes: &[&str], base: Token) -> Token {
+    let prefix = suffixes
+        .iter()
+        .find_map(|suffix| x.strip_suffix(suffix))
+        .unwrap_or(x);
+    if prefix.is_empty() {
+        return base;
+    }
+    match Prefix::from_symbol(prefix) {
+        Some(prefix) => Token::Prefixed(prefix, Box::new(base)),
+        None => Token::INVALID(x.to_string()),
+    }
+}
+
+static PATTERNS: [Pattern; Token::COUNT] = [
+    (DMS_PATTERN, dms),
     (
-        r"(?:(?:[0-9]*\.[0-9]+)|(?:[0-9]+\.))(?:[eE][-+]?[0-9]+|kk|k|m)?|[0-9]+[eE][-+]?[0-9]+",
+        r"(?:(?:[0-9]*\.[0-9]+)|(?:[0-9]+\.))(?:[eE][-+]?[0-9]+|(?:kk|k|m)\b)?|[0-9]+[eE][-+]?[0-9]+",
         |x| {
-            if x.ends_with("kk") {
-                Token::LitFloat(x[..(x.len() - 2)].parse::<f64>().unwrap() * 1e6)
-            } else if x.ends_with("m") {
-                Token::LitFloat(x[..(x.len() - 1)].parse::<f64>().unwrap() * 1e6)
-            } else if x.ends_with("k") {
-                Token::LitFloat(x[..(x.len() - 1)].parse::<f64>().unwrap() * 1e3)
+            if let Some(x) = x.strip_suffix("kk") {
+                decimal(x, 1_000_000)
+            } else if let Some(x) = x.strip_suffix('m') {
+                decimal(x, 1_000_000)
+            } else if let Some(x) = x.strip_suffix('k') {
+                decimal(x, 1_000)
             } else {
-                Token::LitFloat(x.parse::<f64>().unwrap())
+                decimal(x, 1)
             }
         },
     ),
-    (r"[0-9]+(?:kk|k|m)?", |x| {
-        if x.ends_with("kk") {
-            Token::LitInt(x[..(x.len() - 2)].parse::<i64>().unwrap() * 1_000_000)
-        } else if x.ends_with("m") {
-            Token::LitInt(x[..(x.len() - 1)].parse::<i64>().unwrap() * 1_000_000)
-        } else if x.ends_with("k") {
-            Token::LitInt(x[..(x.len() - 1)].parse::<i64>().unwrap() * 1_000)
+    (r"[0-9]+(?:(?:kk|k|m)\b)?", |x| {
+        if let Some(x) = x.strip_suffix("kk") {
+            Token::LitInt(x.parse::<BigInt>().unwrap() * 1_000_000)
+        } else if let Some(x) = x.strip_suffix('m') {
+            Token::LitInt(x.parse::<BigInt>().unwrap() * 1_000_000)
+        } else if let Some(x) = x.strip_suffix('k') {
+            Token::LitInt(x.parse::<BigInt>().unwrap() * 1_000)
         } else {
-            Token::LitInt(x.parse::<i64>().unwrap())
+            Token::LitInt(x.parse::<BigInt>().unwrap())
         }
     }),
     (r"\(", |_| Token::ParBegin),
@@ -39,59 +129,147 @@ static PATTERNS: [(&'static str, fn(&str) -> Token); Token::COUNT] = [
     (r"\-", |_| Token::Sub),
     (r"\+", |_| Token::Add),
     (r"\*", |_| Token::Mul),
+    ("//", |_| Token::IntDiv),
     ("/", |_| Token::Div),
     ("%", |_| Token::Mod),
+    ("!", |_| Token::Fact),
+    (r"\|\|", |_| Token::Parallel),
     (r"to\b", |_| Token::KwTo),
     (r"fixed\b", |_| Token::KwFixed),
     (r"float\b", |_| Token::KwFloat),
     (r"sci\b", |_| Token::KwSci),
     (r"(?:financial|fin)\b", |_| Token::KwFin),
     (r"(?:rational|rat)\b", |_| Token::KwRat),
+    (r"(?:repeating|rep)\b", |_| Token::KwRep),
+    (r"dms\b", |_| Token::KwDms),
     (r"ans\b", |_| Token::KwAns),
-    (CURRENCIES_PATTERN, |x| {
-        Token::Curr(String::from(x.to_ascii_uppercase()))
-    }),
+    (CURRENCIES_PATTERN, |x| Token::Curr(x.to_ascii_uppercase())),
+    ("degrees|degree|deg|°", |_| Token::AngleDeg),
+    ("radians|radian|rad", |_| Token::AngleRad),
+    ("gradians|gradian|grad", |_| Token::AngleGrad),
+    ("turns|turn", |_| Token::AngleTurn),
+    ("arcmin", |_| Token::AngleArcmin),
+    ("arcsec", |_| Token::AngleArcsec),
     // 3 char
-    ("cm3", |_| Token::VolCm),
-    ("mm3", |_| Token::VolMm),
+    // Qualified regional volumes, ahead of `us` (microsecond) and `m`.
+    ("usgallon|usgal", |_| Token::VolGallonUs),
+    ("impgallon|impgal", |_| Token::VolGallonImp),
+    ("uspint|uspt", |_| Token::VolPintUs),
+    ("imppint|imppt", |_| Token::VolPintImp),
+    ("uscup", |_| Token::VolCupUs),
+    ("mcup", |_| Token::VolCupMetric),
+    ("usfloz", |_| Token::VolFlOzUs),
+    ("impfloz", |_| Token::VolFlOzImp),
+    ("ustbsp", |_| Token::VolTbspUs),
+    ("mtbsp", |_| Token::VolTbspMetric),
+    ("ustsp", |_| Token::VolTspUs),
+    ("mtsp", |_| Token::VolTspMetric),
+    ("fl ?oz", |_| Token::VolFlOz),
+    ("tbsp", |_| Token::VolTbsp),
+    ("tsp", |_| Token::VolTsp),
+    // `ton` must not take the start of `tonne`, which would then read as an
+    // identifier, hence the word boundaries.
+    (r"tons\b|ton\b", |_| Token::MassTon),
+    ("tonnes|tonne|t", |_| Token::MassTonne),
+    ("shortton|short_ton", |_| Token::MassShortTon),
+    ("longton|long_ton", |_| Token::MassLongTon),
+    ("ozt", |_| Token::MassTroyOunce),
+    ("angstroms|angstrom|Å", |_| Token::LenAngstrom),
+    ("lightyears|lightyear|ly", |_| Token::LenLightYear),
+    ("parsecs|parsec|pc", |_| Token::LenParsec),
+    ("furlongs|furlong", |_| Token::LenFurlong),
+    ("fortnights|fortnight", |_| Token::TimeFortnight),
+    ("chains|chain|ch", |_| Token::LenChain),
+    ("AU", |_| Token::LenAu),
+    ("amperes|ampere|amps|amp|A", |_| Token::ElecAmpere),
+    ("picas|pica", |_| Token::LenPica),
+    ("ares|are", |_| Token::AreaAre),
+    ("rpm", |_| Token::FreqRpm),
+    ("volts|volt|V", |_| Token::ElecVolt),
+    ("farads|farad", |_| Token::ElecFarad),
+    ("coulombs|coulomb", |_| Token::ElecCoulomb),
+    ("ohms|ohm|Ω", |_| Token::ElecOhm),
+    ("bpm", |_| Token::FreqBpm),
+    ("acres|acre|ac", |_| Token::AreaAcre),
+    ("sqrod", |_| Token::AreaSquareRod),
+    ("négyszögöl|negyszogol|nöl", |_| Token::AreaNegyszogol),
+    ("hold|kh", |_| Token::AreaHold),
+    ("L/100km|l/100km", |_| Token::FuelL100km),
+    ("mpg_imp", |_| Token::FuelMpgImp),
+    ("mpg", |_| Token::FuelMpg),
+    ("BTU|Btu|btu", |_| Token::EnergyBtu),
+    ("(?:[kMGT]|[KMGT]i)?(?:bits|bit)", |x| {
+        data(x, &["bits", "bit"], Token::DataBit)
+    }),
+    ("(?:[kMGT]|[KMGT]i)?(?:bytes|byte|B)", |x| {
+        data(x, &["bytes", "byte", "B"], Token::DataByte)
+    }),
     ("in3", |_| Token::VolInch),
     ("ft3", |_| Token::VolFeet),
     ("yd3", |_| Token::VolYard),
     ("gallon|gal", |_| Token::VolGallon),
     ("cup", |_| Token::VolCup),
-    ("km2", |_| Token::AreaKm),
-    ("cm2", |_| Token::AreaCm),
-    ("mm2", |_| Token::AreaMm),
+    ("cal", |_| Token::EnergyCal),
+    ("mmHg", |_| Token::PressureMmHg),
+    ("psi", |_| Token::PressurePsi),
+    ("atm", |_| Token::PressureAtm),
+    ("bar", |_| Token::PressureBar),
     ("in2", |_| Token::AreaInch),
     ("ft2", |_| Token::AreaFeet),
     ("yd2", |_| Token::AreaYard),
     ("mi2", |_| Token::AreaMile),
-    ("min", |_| Token::TimeMin),
+    ("minutes|minute|min", |_| Token::TimeMin),
+    ("nmi", |_| Token::LenNauticalMile),
+    ("mph", |_| Token::SpeedMph),
+    ("knots|knot|kn", |_| Token::SpeedKnot),
     // 2 char
-    ("pint|pt", |_| Token::VolPint),
-    ("ml", |_| Token::VolMilliLiter),
-    ("km", |_| Token::LenKm),
-    ("cm", |_| Token::LenCm),
-    ("mm", |_| Token::LenMm),
+    (PREFIXED_PATTERN, prefixed),
+    ("ΔC|dC", |_| Token::TempDeltaC),
+    ("ΔF|dF", |_| Token::TempDeltaF),
+    ("ΔK|dK", |_| Token::TempDeltaK),
+    ("ΔR|dR", |_| Token::TempDeltaR),
+    // The typographic point is `pts`: `pt` has always been the pint.
+    ("pts", |_| Token::LenPoint),
+    ("pints|pint|pt", |_| Token::VolPint),
+    ("px", |_| Token::LenPixel),
+    ("em", |_| Token::LenEm),
+    ("Wh", |_| Token::EnergyWh),
+    ("Pa", |_| Token::PressurePa),
+    ("Hz", |_| Token::FreqHz),
+    ("henries|henrys|henry|H", |_| Token::ElecHenry),
+    ("hp", |_| Token::PowerHp),
     ("inch|in|\"", |_| Token::LenInch),
     ("feet|ft|'", |_| Token::LenFeet),
     ("yard|yd", |_| Token::LenYard),
     ("mi", |_| Token::LenMile),
     ("m2", |_| Token::AreaM),
     ("m3", |_| Token::VolM),
-    ("kg", |_| Token::MassKg),
     ("ounce|oz", |_| Token::MassOunce),
+    ("stones|stone|st", |_| Token::MassStone),
+    ("ha", |_| Token::AreaHectare),
+    ("weeks|week|wk", |_| Token::TimeWeek),
+    ("months|month|mo", |_| Token::TimeMonth),
+    ("years|year|yr", |_| Token::TimeYear),
+    ("carats|carat|ct", |_| Token::MassCarat),
+    ("grains|grain|gr", |_| Token::MassGrain),
     ("pound|lb", |_| Token::MassPound),
     // 1 char
-    ("sec|s", |_| Token::TimeSec),
-    ("hour|hr|h", |_| Token::TimeHour),
+    ("seconds|second|sec|s", |_| Token::TimeSec),
+    ("hours|hour|hr|h", |_| Token::TimeHour),
+    ("days|day|d", |_| Token::TimeDay),
     ("m", |_| Token::LenM),
     ("C|c", |_| Token::TempC),
     ("F|f", |_| Token::TempF),
+    ("kelvin|K", |_| Token::TempK),
+    ("rankine|R", |_| Token::TempR),
     ("g", |_| Token::MassG),
-    ("liter|l", |_| Token::VolLiter),
+    ("liter|l|L", |_| Token::VolLiter),
+    ("J", |_| Token::EnergyJoule),
+    ("W", |_| Token::PowerWatt),
     (r"\|", |_| Token::Pipe),
-    ("[A-Za-z_][A-Za-z0-9_]*", |x| Token::Ident(String::from(x))),
+    ("=", |_| Token::Assign),
+    (",", |_| Token::Comma),
+    (IDENT_PATTERN, |x| Token::Ident(String::from(x))),
     (r"\S+", |x| Token::INVALID(String::from(x))),
 ];
 
@@ -103,8 +281,12 @@ impl Lexer {
             .collect::<Vec<String>>()
             .join("|");
         let pattern = Regex::new(pattern.as_str()).unwrap();
+        let ident = Regex::new(&format!("^{IDENT_PATTERN}")).unwrap();
 
-        Lexer { patterns: pattern }
+        Lexer {
+            patterns: pattern,
+            ident,
+        }
     }
 
     fn map_captures(captures: Captures) -> Token {
@@ -112,14 +294,35 @@ impl Lexer {
             if let Some(m) = captures.get(idx) {
                 let found = m.as_str();
                 let (_, extract) = PATTERNS[idx - 1];
-                let token = extract(found);
-                return token;
+                return extract(found);
             }
         }
-        return Token::INVALID(String::from(""));
+        Token::INVALID(String::from(""))
     }
 
     pub fn parse<'a>(&'a self, text: &'a str) -> impl Iterator<Item = Token> + 'a {
-        self.patterns.captures_iter(text).map(Lexer::map_captures)
+        let mut pos = 0;
+        std::iter::from_fn(move || {
+            let captures = self.patterns.captures_at(text, pos)?;
+            let found = captures.get(0).expect("group 0 is the whole match");
+            // A unit or keyword running straight into more letters is the start
+            // of a longer name (`speed`, not `s` + `peed`), so re-read it as an
+            // identifier. Digits don't extend it: `5ft11in` stays compound.
+            let starts_word = found
+                .as_str()
+                .starts_with(|c: char| c.is_ascii_alphabetic());
+            let continues =
+                text[found.end()..].starts_with(|c: char| c.is_ascii_alphabetic() || c == '_');
+            if starts_word && continues {
+                let ident = self
+                    .ident
+                    .find(&text[found.start()..])
+                    .expect("starts with a letter");
+                pos = found.start() + ident.end();
+                return Some(Token::Ident(ident.as_str().to_string()));
+            }
+            pos = found.end();
+            Some(Lexer::map_captures(captures))
+        })
     }
 }
@@ -1,7 +1,11 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::mem;
+use std::rc::Rc;
 
+use crate::builtin::Builtin;
 use crate::config::FormatSpec;
-use crate::node::Node;
+use crate::error::CalcError;
+use crate::node::{Node, Statement};
 use crate::number::Number;
 use crate::parser::token::{Token, CURRENCIES};
 use crate::unit;
@@ -17,10 +21,28 @@ enum Match<T> {
 
 type MemoPos = (usize, &'static str);
 
+/// A user-defined function such as `area(w, h) = w * h`. The body is kept as
+/// tokens and parsed again on each line that calls it, so it sees the variables
+/// and functions current at the call. Within a line the parsed body is shared
+/// by every call, and the arguments are bound when the line is evaluated.
+#[derive(Clone)]
+pub struct Function {
+    params: Vec<Token>,
+    body: Vec<Token>,
+    /// The definition as typed, for `/funcs`.
+    pub text: String,
+}
+
 pub struct Parser {
     tokens: Vec<Token>,
     memos: HashMap<MemoPos, Match<Node>>,
     ans: Value,
+    vars: BTreeMap<String, Value>,
+    funcs: BTreeMap<String, Function>,
+    /// Parameter tokens of the function body being parsed.
+    params: Vec<Token>,
+    /// Function bodies already parsed on this line, by name.
+    bodies: HashMap<String, Rc<Node>>,
 }
 
 impl Parser {
@@ -29,6 +51,10 @@ impl Parser {
             tokens: vec![],
             memos: HashMap::new(),
             ans: Value::from(0i64),
+            vars: BTreeMap::new(),
+            funcs: BTreeMap::new(),
+            params: vec![],
+            bodies: HashMap::new(),
         }
     }
 
@@ -36,6 +62,32 @@ impl Parser {
         self.ans = val;
     }
 
+    pub fn set_var(&mut self, name: String, val: Value) {
+        self.vars.insert(name, val);
+    }
+
+    pub fn unset_var(&mut self, name: &str) -> Option<Value> {
+        self.vars.remove(name)
+    }
+
+    /// Variables in name order.
+    pub fn vars(&self) -> impl Iterator<Item = (&String, &Value)> {
+        self.vars.iter()
+    }
+
+    pub fn define(&mut self, name: String, func: Function) {
+        self.funcs.insert(name, func);
+    }
+
+    pub fn undefine(&mut self, name: &str) -> Option<Function> {
+        self.funcs.remove(name)
+    }
+
+    /// Functions in name order.
+    pub fn funcs(&self) -> impl Iterator<Item = (&String, &Function)> {
+        self.funcs.iter()
+    }
+
     pub fn is_empty(&self) -> bool {
         self.tokens.is_empty()
     }
@@ -48,11 +100,258 @@ impl Parser {
         self.tokens.clear();
     }
 
-    pub fn parse(&mut self) -> Option<(Node, Option<FormatSpec>)> {
+    pub fn parse(&mut self) -> Option<(Statement, Option<FormatSpec>)> {
         // TODO: we should be able to re-use the previous computations
         self.memos.clear();
+        self.bodies.clear();
+        if let Some(literal) = self.out_of_range() {
+            return Some((
+                Statement::Invalid(CalcError::NumberOutOfRange(literal)),
+                None,
+            ));
+        }
+        if let Some((name, params, pos)) = self.expect_signature() {
+            return match name {
+                Token::Ident(name) => self.parse_definition(name, params, pos),
+                _ => Some((Statement::Invalid(self.reserved_name()?), None)),
+            };
+        }
+        if self.expect(1, Token::Assign).is_some() {
+            let (node, spec) = self.parse_expression(2)?;
+            let statement = match &self.tokens[0] {
+                Token::Ident(name) => Statement::Assign(name.clone(), node),
+                _ => Statement::Invalid(self.reserved_name()?),
+            };
+            return Some((statement, spec));
+        }
+        let (node, spec) = self.parse_expression(0)?;
+        Some((Statement::Expr(node), spec))
+    }
+
+    /// A number literal the lexer rejected (`1e1001`: its exponent is beyond
+    /// what `Rational::from_str` expands exactly). Any other invalid token just
+    /// fails to parse.
+    fn out_of_range(&self) -> Option<String> {
+        self.tokens.iter().find_map(|token| match token {
+            Token::INVALID(x) if x.starts_with(|c: char| c.is_ascii_digit()) => Some(x.clone()),
+            _ => None,
+        })
+    }
+
+    /// The error for assigning to or defining the unit or keyword that starts
+    /// the line (`f = 3`, `s(x) = x`), which can never be read back as a name.
+    fn reserved_name(&mut self) -> Option<CalcError> {
+        if let Some(Token::KwAns) = self.tokens.first() {
+            return Some(CalcError::ReservedName("ans".to_string()));
+        }
+        match self.expect_unit(0) {
+            Match::Ok(unit, 1) => Some(CalcError::ReservedName(unit::get_unit_name(&unit))),
+            _ => None,
+        }
+    }
+
+    /// `name(p1, p2, ...) =`, returning the name token, the parameter tokens and
+    /// the position of the body. Parameters may be spelled like units (`h`),
+    /// since inside the body they shadow them; duplicates are left to
+    /// `parse_definition` to report.
+    fn expect_signature(&mut self) -> Option<(Token, Vec<Token>, usize)> {
+        let name = self.tokens.first()?.clone();
+        let mut pos = self.expect(1, Token::ParBegin)?;
+        let mut params: Vec<Token> = vec![];
+        if let Some(next) = self.expect(pos, Token::ParEnd) {
+            pos = next;
+        } else {
+            loop {
+                let param = self.tokens.get(pos)?.clone();
+                let is_name = matches!(param, Token::Ident(_))
+                    || matches!(self.expect_unit(pos), Match::Ok(_, next) if next == pos + 1);
+                if !is_name {
+                    return None;
+                }
+                params.push(param);
+                if let Some(next) = self.expect(pos + 1, Token::Comma) {
+                    pos = next;
+                } else {
+                    pos = self.expect(pos + 1, Token::ParEnd)?;
+                    break;
+                }
+            }
+        }
+        let pos = self.expect(pos, Token::Assign)?;
+        Some((name, params, pos))
+    }
+
+    fn parse_definition(
+        &mut self,
+        name: String,
+        params: Vec<Token>,
+        pos: usize,
+    ) -> Option<(Statement, Option<FormatSpec>)> {
+        if Builtin::from_name(&name).is_some() {
+            return Some((Statement::Invalid(CalcError::BuiltinFunction(name)), None));
+        }
+        if let Some(dup) = (1..params.len()).find(|&i| params[..i].contains(&params[i])) {
+            let param = self.param_name(dup);
+            return Some((
+                Statement::Invalid(CalcError::DuplicateParameter(param)),
+                None,
+            ));
+        }
+        let func = Function {
+            params,
+            body: self.tokens[pos..].to_vec(),
+            text: String::new(),
+        };
+        // Check the body parses now. A body that may still be unfinished
+        // (`f(x) = x +`) returns `None` so the REPL reads on.
+        if self.parse_body(&func).is_none() {
+            let err = self.shadowed_unit(&func)?;
+            return Some((Statement::Invalid(err), None));
+        }
+        if self.calls_into(&func.body, &name) {
+            return Some((Statement::Invalid(CalcError::RecursiveFunction(name)), None));
+        }
+        Some((Statement::Define(name, func), None))
+    }
+
+    /// The `idx`-th parameter of the signature being parsed, as written.
+    fn param_name(&mut self, idx: usize) -> String {
+        // `name ( p0 , p1 , ...`: parameters sit at every other token.
+        let pos = 2 + 2 * idx;
+        match &self.tokens[pos] {
+            Token::Ident(name) => name.clone(),
+            _ => match self.expect_unit(pos) {
+                Match::Ok(unit, _) => unit::get_unit_name(&unit),
+                Match::Err => String::new(),
+            },
+        }
+    }
+
+    /// The error for a body that only parses with its unit-spelled parameters
+    /// read as units (`f(h) = 2 h`): no further input can complete it.
+    fn shadowed_unit(&mut self, func: &Function) -> Option<CalcError> {
+        let idx = func
+            .params
+            .iter()
+            .position(|param| !matches!(param, Token::Ident(_)) && func.body.contains(param))?;
+        let unbound = Function {
+            params: vec![],
+            body: func.body.clone(),
+            text: String::new(),
+        };
+        self.parse_body(&unbound)?;
+        Some(CalcError::ShadowedUnit(self.param_name(idx)))
+    }
+
+    /// Whether `tokens` call `target`, directly or through defined functions.
+    /// Rejecting such definitions keeps call expansion finite.
+    fn calls_into(&self, tokens: &[Token], target: &str) -> bool {
+        let mut pending = vec![tokens];
+        let mut seen = HashSet::new();
+        while let Some(tokens) = pending.pop() {
+            for pair in tokens.windows(2) {
+                let [Token::Ident(name), Token::ParBegin] = pair else {
+                    continue;
+                };
+                if name == target {
+                    return true;
+                }
+                if seen.insert(name) {
+                    if let Some(func) = self.funcs.get(name) {
+                        pending.push(&func.body);
+                    }
+                }
+            }
+        }
+        false
+    }
+
+    /// Parse a function body, with its parameters read as `Node::Param`. The
+    /// variables, functions and parsed bodies move into the body's parser and
+    /// back, so nested calls share them instead of copying.
+    fn parse_body(&mut self, func: &Function) -> Option<Node> {
+        let mut body = Parser {
+            tokens: func.body.clone(),
+            memos: HashMap::new(),
+            ans: self.ans.clone(),
+            vars: mem::take(&mut self.vars),
+            funcs: mem::take(&mut self.funcs),
+            params: func.params.clone(),
+            bodies: mem::take(&mut self.bodies),
+        };
+        let node = match body.expression(0) {
+            Match::Ok(node, pos) if pos == body.tokens.len() => Some(node),
+            _ => None,
+        };
+        self.vars = body.vars;
+        self.funcs = body.funcs;
+        self.bodies = body.bodies;
+        node
+    }
+
+    fn call(&mut self, name: &str, args: Vec<Node>) -> Option<Node> {
+        let Some(func) = self.funcs.get(name) else {
+            return Some(Node::BadCall {
+                name: name.to_string(),
+                args: args.len(),
+                expected: None,
+            });
+        };
+        if func.params.len() != args.len() {
+            return Some(Node::BadCall {
+                name: name.to_string(),
+                args: args.len(),
+                expected: Some(func.params.len()),
+            });
+        }
+        let body = match self.bodies.get(name) {
+            Some(body) => body.clone(),
+            None => {
+                let func = func.clone();
+                let body = Rc::new(self.parse_body(&func)?);
+                self.bodies.insert(name.to_string(), body.clone());
+                body
+            }
+        };
+        Some(Node::Call {
+            name: name.to_string(),
+            body,
+            args,
+        })
+    }
+
+    /// The index of the parameter token at `pos`, if it is one.
+    fn bound(&self, pos: usize) -> Option<usize> {
+        let tok = self.tokens.get(pos)?;
+        self.params.iter().position(|param| param == tok)
+    }
+
+    /// `expression ("," expression)* ")"`, or just `")"`.
+    fn expect_args(&mut self, pos: usize) -> Match<Vec<Node>> {
+        if let Some(pos) = self.expect(pos, Token::ParEnd) {
+            return Match::Ok(vec![], pos);
+        }
+        let mut args = vec![];
+        let mut pos = pos;
+        loop {
+            let Match::Ok(arg, next) = self.expression(pos) else {
+                return Match::Err;
+            };
+            args.push(arg);
+            if let Some(next) = self.expect(next, Token::Comma) {
+                pos = next;
+            } else if let Some(next) = self.expect(next, Token::ParEnd) {
+                return Match::Ok(args, next);
+            } else {
+                return Match::Err;
+            }
+        }
+    }
+
+    /// An expression running to the end of input, with an optional `| formatter`.
+    fn parse_expression(&mut self, pos: usize) -> Option<(Node, Option<FormatSpec>)> {
         let pos_end = self.tokens.len();
-        if let Match::Ok(node, pos) = self.expression(0) {
+        if let Match::Ok(node, pos) = self.expression(pos) {
             if pos == pos_end {
                 return Some((node, None));
             }
@@ -62,7 +361,7 @@ impl Parser {
                 }
             }
         }
-        return None;
+        None
     }
 
     fn parse_format_clause(&self, pos: usize) -> Option<(FormatSpec, usize)> {
@@ -85,15 +384,17 @@ impl Parser {
                 Some((FormatSpec::Financial { precision }, pos))
             }
             Token::KwRat => Some((FormatSpec::Rational, pos + 1)),
+            Token::KwRep => Some((FormatSpec::Repeating, pos + 1)),
+            Token::KwDms => Some((FormatSpec::Dms, pos + 1)),
             _ => None,
         }
     }
 
     fn expect_precision(&self, pos: usize) -> (Option<u8>, usize) {
         if pos < self.tokens.len() {
-            if let &Token::LitInt(n) = &self.tokens[pos] {
-                if (0..=255).contains(&n) {
-                    return (Some(n as u8), pos + 1);
+            if let Token::LitInt(n) = &self.tokens[pos] {
+                if let Ok(n) = u8::try_from(n) {
+                    return (Some(n), pos + 1);
                 }
             }
         }
@@ -117,7 +418,7 @@ impl Parser {
 
         let result = base(self, pos);
         self.memos.insert(key, result.clone());
-        return result;
+        result
     }
 
     fn memoize_left_rec(
@@ -147,14 +448,14 @@ impl Parser {
             self.memos.insert(key, last_result.clone());
         }
 
-        return last_result;
+        last_result
     }
 
     fn expect(&self, pos: usize, tok: Token) -> Option<usize> {
         if pos < self.tokens.len() && self.tokens[pos] == tok {
             return Some(pos + 1);
         }
-        return None;
+        None
     }
 
     fn num_unit(&mut self, pos: usize) -> Match<Node> {
@@ -186,8 +487,11 @@ impl Parser {
     }
 
     fn expect_single_num_unit(&mut self, pos: usize) -> Match<(Number, Unit)> {
+        if let Some(Token::LitDms(degrees)) = self.tokens.get(pos) {
+            return Match::Ok((degrees.clone().into(), Unit::AngleDeg), pos + 1);
+        }
         if let Match::Ok(num, pos) = self.expect_number(pos) {
-            if let Match::Ok(unit, pos) = self.expect_unit(pos) {
+            if let Match::Ok(unit, pos) = self.expect_unit_expr(pos) {
                 return Match::Ok((num, unit), pos);
             }
         }
@@ -206,9 +510,43 @@ impl Parser {
                 }
             }
         }
+        if let Some(idx) = self.bound(pos) {
+            return Match::Ok(Node::Param(idx), pos + 1);
+        }
         if let Some(next) = self.expect(pos, Token::KwAns) {
             return Match::Ok(Node::Value(self.ans.clone()), next);
         }
+        // A call holds the function's parsed body and its argument nodes. `min`
+        // lexes as the minute unit, so it is recognised here by the parenthesis.
+        let name = match self.tokens.get(pos) {
+            Some(Token::Ident(name)) => Some(name.clone()),
+            Some(Token::TimeMin) => Some(Builtin::Min.name().to_string()),
+            _ => None,
+        };
+        if let Some(name) = name {
+            if let Some(args_pos) = self.expect(pos + 1, Token::ParBegin) {
+                if let Match::Ok(args, next) = self.expect_args(args_pos) {
+                    if let Some(func) = Builtin::from_name(&name) {
+                        return Match::Ok(Node::Builtin { func, args }, next);
+                    }
+                    if let Some(node) = self.call(&name, args) {
+                        return Match::Ok(node, next);
+                    }
+                }
+            }
+        }
+        // Like `ans`, variables are substituted when the line is parsed. A
+        // variable shadows a custom unit of the same name; otherwise the custom
+        // unit is read below like any other bare unit.
+        if let Some(Token::Ident(name)) = self.tokens.get(pos) {
+            match self.vars.get(name) {
+                Some(val) => return Match::Ok(Node::Value(val.clone()), pos + 1),
+                None if unit::custom(name).is_none() => {
+                    return Match::Ok(Node::Var(name.clone()), pos + 1)
+                }
+                None => {}
+            }
+        }
         if let Match::Ok(node, pos) = self.num_unit(pos) {
             return Match::Ok(node, pos);
         }
@@ -221,12 +559,31 @@ impl Parser {
         Match::Err
     }
 
+    fn factorial(&mut self, pos: usize) -> Match<Node> {
+        self.memoize_left_rec(pos, "factorial", Self::factorial_inner)
+    }
+
+    fn factorial_inner(&mut self, pos: usize) -> Match<Node> {
+        if let Match::Ok(val, pos) = self.factorial(pos) {
+            if let Some(pos) = self.expect(pos, Token::Fact) {
+                return Match::Ok(
+                    Node::UnaryExpr {
+                        op: UnaryOp::Fact,
+                        val: Box::new(val),
+                    },
+                    pos,
+                );
+            }
+        }
+        self.atom(pos)
+    }
+
     fn exponent(&mut self, pos: usize) -> Match<Node> {
         self.memoize(pos, "exponent", Self::exponent_inner)
     }
 
     fn exponent_inner(&mut self, pos: usize) -> Match<Node> {
-        if let Match::Ok(lhs, pos) = self.atom(pos) {
+        if let Match::Ok(lhs, pos) = self.factorial(pos) {
             if let Some(pos) = self.expect(pos, Token::Exp) {
                 if let Match::Ok(rhs, pos) = self.exponent(pos) {
                     return Match::Ok(
@@ -240,7 +597,28 @@ impl Parser {
                 }
             }
         }
-        self.atom(pos)
+        self.factorial(pos)
+    }
+
+    fn unary(&mut self, pos: usize) -> Match<Node> {
+        self.memoize(pos, "unary", Self::unary_inner)
+    }
+
+    // Unary minus binds tighter than `*`, `//` and `%` (so `-7 % 3` is 2, as
+    // `(-7) % 3`) but looser than `^`, keeping `-2^2` at -4.
+    fn unary_inner(&mut self, pos: usize) -> Match<Node> {
+        if let Some(pos) = self.expect(pos, Token::Sub) {
+            if let Match::Ok(val, pos) = self.unary(pos) {
+                return Match::Ok(
+                    Node::UnaryExpr {
+                        op: UnaryOp::Neg,
+                        val: Box::new(val),
+                    },
+                    pos,
+                );
+            }
+        }
+        self.exponent(pos)
     }
 
     fn term(&mut self, pos: usize) -> Match<Node> {
@@ -250,7 +628,7 @@ impl Parser {
     fn term_inner(&mut self, pos: usize) -> Match<Node> {
         if let Match::Ok(lhs, pos) = self.term(pos) {
             if let Some(pos) = self.expect(pos, Token::Mul) {
-                if let Match::Ok(rhs, pos) = self.exponent(pos) {
+                if let Match::Ok(rhs, pos) = self.unary(pos) {
                     return Match::Ok(
                         Node::BinaryExpr {
                             op: BinaryOp::Mul,
@@ -261,7 +639,7 @@ impl Parser {
                     );
                 }
             } else if let Some(pos) = self.expect(pos, Token::Div) {
-                if let Match::Ok(rhs, pos) = self.exponent(pos) {
+                if let Match::Ok(rhs, pos) = self.unary(pos) {
                     return Match::Ok(
                         Node::BinaryExpr {
                             op: BinaryOp::Div,
@@ -271,8 +649,41 @@ impl Parser {
                         pos,
                     );
                 }
+            } else if let Some(pos) = self.expect(pos, Token::IntDiv) {
+                if let Match::Ok(rhs, pos) = self.unary(pos) {
+                    return Match::Ok(
+                        Node::BinaryExpr {
+                            op: BinaryOp::IntDiv,
+                            lhs: Box::new(lhs),
+                            rhs: Box::new(rhs),
+                        },
+                        pos,
+                    );
+                }
+            } else if let Some(pos) = self.expect(pos, Token::Mod) {
+                if let Match::Ok(rhs, pos) = self.unary(pos) {
+                    return Match::Ok(
+                        Node::BinaryExpr {
+                            op: BinaryOp::Mod,
+                            lhs: Box::new(lhs),
+                            rhs: Box::new(rhs),
+                        },
+                        pos,
+                    );
+                }
+            } else if let Some(pos) = self.expect(pos, Token::Parallel) {
+                if let Match::Ok(rhs, pos) = self.unary(pos) {
+                    return Match::Ok(
+                        Node::BinaryExpr {
+                            op: BinaryOp::Parallel,
+                            lhs: Box::new(lhs),
+                            rhs: Box::new(rhs),
+                        },
+                        pos,
+                    );
+                }
             } else if let Some(pos) = self.expect(pos, Token::KwTo) {
-                if let Match::Ok(unit, pos) = self.expect_unit(pos) {
+                if let Match::Ok(unit, pos) = self.expect_unit_expr(pos) {
                     return Match::Ok(
                         Node::BinaryExpr {
                             op: BinaryOp::Conversion,
@@ -283,12 +694,12 @@ impl Parser {
                     );
                 }
             } else if let Match::Ok(unit, pos) = self.expect_unit(pos) {
-                // Juxtaposition with a bare unit is multiplication by `1·unit`,
-                // so `(2*3) eur` → `6 eur`. (`5 m` literals are handled by
+                // Juxtaposition with a bare unit attaches `1·unit`, so
+                // `(2*3) eur` → `6 eur`. (`5 m` literals are handled by
                 // `num_unit`; both yield the same result.)
                 return Match::Ok(
                     Node::BinaryExpr {
-                        op: BinaryOp::Mul,
+                        op: BinaryOp::Attach,
                         lhs: Box::new(lhs),
                         rhs: Box::new(Node::value(1.into(), Some(unit))),
                     },
@@ -296,7 +707,7 @@ impl Parser {
                 );
             }
         }
-        self.exponent(pos)
+        self.unary(pos)
     }
 
     fn expression(&mut self, pos: usize) -> Match<Node> {
@@ -329,17 +740,6 @@ impl Parser {
                 }
             }
         }
-        if let Some(pos) = self.expect(pos, Token::Sub) {
-            if let Match::Ok(rhs, pos) = self.term(pos) {
-                return Match::Ok(
-                    Node::UnaryExpr {
-                        op: UnaryOp::Neg,
-                        val: Box::new(rhs),
-                    },
-                    pos,
-                );
-            }
-        }
         self.term(pos)
     }
 
@@ -347,9 +747,9 @@ impl Parser {
         if pos >= self.tokens.len() {
             return Match::Err;
         }
-        let num: Option<Number> = match self.tokens[pos] {
-            Token::LitInt(val) => Some(val.into()),
-            Token::LitFloat(val) => Some(val.into()),
+        let num: Option<Number> = match &self.tokens[pos] {
+            Token::LitInt(val) => Some(val.clone().into()),
+            Token::LitDec(val) => Some(val.clone().into()),
             _ => None,
         };
         if let Some(num) = num {
@@ -359,60 +759,186 @@ impl Parser {
         }
     }
 
-    fn expect_unit(&mut self, pos: usize) -> Match<Unit> {
-        if pos >= self.tokens.len() {
+    /// A unit, or a product/quotient of units with optional integer powers
+    /// (`km/h`, `kg*m/s^2`). It binds tighter than arithmetic, so in
+    /// `10 km / 50 km/h` the divisor is the speed `50 km/h`.
+    fn expect_unit_expr(&mut self, pos: usize) -> Match<Unit> {
+        let Match::Ok(mut unit, mut pos) = self.expect_unit_power(pos) else {
             return Match::Err;
+        };
+        loop {
+            let (exp, next) = if let Some(next) = self.expect(pos, Token::Mul) {
+                (1, next)
+            } else if let Some(next) = self.expect(pos, Token::Div) {
+                (-1, next)
+            } else {
+                break;
+            };
+            let Match::Ok(rhs, next) = self.expect_unit_power(next) else {
+                break;
+            };
+            // The merge factor is irrelevant here: only the resulting unit matters.
+            let Ok((_, Some(combined))) = unit::combine(Some(unit.clone()), Some(rhs), exp) else {
+                break;
+            };
+            unit = combined;
+            pos = next;
         }
-        let unit = match &self.tokens[pos] {
-            Token::LenM => Some(Unit::LenM),
-            Token::LenKm => Some(Unit::LenKm),
-            Token::LenCm => Some(Unit::LenCm),
-            Token::LenMm => Some(Unit::LenMm),
-            Token::LenInch => Some(Unit::LenInch),
-            Token::LenFeet => Some(Unit::LenFeet),
-            Token::LenYard => Some(Unit::LenYard),
-            Token::LenMile => Some(Unit::LenMile),
-            Token::AreaM => Some(Unit::AreaM),
-            Token::AreaKm => Some(Unit::AreaKm),
-            Token::AreaCm => Some(Unit::AreaCm),
-            Token::AreaMm => Some(Unit::AreaMm),
-            Token::AreaInch => Some(Unit::AreaInch),
-            Token::AreaFeet => Some(Unit::AreaFeet),
-            Token::AreaYard => Some(Unit::AreaYard),
-            Token::AreaMile => Some(Unit::AreaMile),
-            Token::VolLiter => Some(Unit::VolLiter),
-            Token::VolMilliLiter => Some(Unit::VolMilliLiter),
-            Token::VolM => Some(Unit::VolM),
-            Token::VolCm => Some(Unit::VolCm),
-            Token::VolMm => Some(Unit::VolMm),
-            Token::VolInch => Some(Unit::VolInch),
-            Token::VolFeet => Some(Unit::VolFeet),
-            Token::VolYard => Some(Unit::VolYard),
-            Token::VolPint => Some(Unit::VolPint),
-            Token::VolGallon => Some(Unit::VolGallon),
-            Token::VolCup => Some(Unit::VolCup),
-            Token::MassG => Some(Unit::MassG),
-            Token::MassKg => Some(Unit::MassKg),
-            Token::MassOunce => Some(Unit::MassOunce),
-            Token::MassPound => Some(Unit::MassPound),
-            Token::TempC => Some(Unit::TempC),
-            Token::TempF => Some(Unit::TempF),
-            Token::TimeHour => Some(Unit::TimeHour),
-            Token::TimeMin => Some(Unit::TimeMin),
-            Token::TimeSec => Some(Unit::TimeSec),
-            Token::Curr(name) => {
-                if let Ok(idx) = CURRENCIES.binary_search_by(|p: &&str| (*p).cmp(name.as_str())) {
-                    Some(Unit::Curr(CURRENCIES[idx]))
-                } else {
-                    None
+        Match::Ok(unit, pos)
+    }
+
+    fn expect_unit_power(&mut self, pos: usize) -> Match<Unit> {
+        let Match::Ok(unit, pos) = self.expect_unit(pos) else {
+            return Match::Err;
+        };
+        if let Some(next) = self.expect(pos, Token::Exp) {
+            if let Some(Token::LitInt(exp)) = self.tokens.get(next) {
+                if let Some(unit) = i32::try_from(exp)
+                    .ok()
+                    .and_then(|exp| unit::pow(unit.clone(), exp))
+                {
+                    return Match::Ok(unit, next + 1);
                 }
             }
-            _ => None,
-        };
-        if let Some(unit) = unit {
+        }
+        Match::Ok(unit, pos)
+    }
+
+    fn expect_unit(&mut self, pos: usize) -> Match<Unit> {
+        // A parameter spelled like a unit (`h` in `area(w, h)`) is not that unit.
+        if pos >= self.tokens.len() || self.bound(pos).is_some() {
+            return Match::Err;
+        }
+        if let Some(unit) = token_unit(&self.tokens[pos]) {
             Match::Ok(unit, pos + 1)
         } else {
             Match::Err
         }
     }
 }
+
+/// The unit a token spells, if any.
+fn token_unit(tok: &Token) -> Option<Unit> {
+    match tok {
+        Token::LenM => Some(Unit::LenM),
+        Token::LenInch => Some(Unit::LenInch),
+        Token::LenFeet => Some(Unit::LenFeet),
+        Token::LenYard => Some(Unit::LenYard),
+        Token::LenMile => Some(Unit::LenMile),
+        Token::LenNauticalMile => Some(Unit::LenNauticalMile),
+        Token::LenAngstrom => Some(Unit::LenAngstrom),
+        Token::LenAu => Some(Unit::LenAu),
+        Token::LenLightYear => Some(Unit::LenLightYear),
+        Token::LenParsec => Some(Unit::LenParsec),
+        Token::LenFurlong => Some(Unit::LenFurlong),
+        Token::LenChain => Some(Unit::LenChain),
+        Token::AreaM => Some(Unit::AreaM),
+        Token::AreaInch => Some(Unit::AreaInch),
+        Token::AreaFeet => Some(Unit::AreaFeet),
+        Token::AreaYard => Some(Unit::AreaYard),
+        Token::AreaMile => Some(Unit::AreaMile),
+        Token::LenPoint => Some(Unit::LenPoint),
+        Token::LenPica => Some(Unit::LenPica),
+        Token::LenPixel => Some(Unit::LenPixel),
+        Token::LenEm => Some(Unit::LenEm),
+        Token::AreaAre => Some(Unit::AreaAre),
+        Token::AreaHectare => Some(Unit::AreaHectare),
+        Token::AreaAcre => Some(Unit::AreaAcre),
+        Token::AreaSquareRod => Some(Unit::AreaSquareRod),
+        Token::AreaNegyszogol => Some(Unit::AreaNegyszogol),
+        Token::AreaHold => Some(Unit::AreaHold),
+        Token::VolLiter => Some(Unit::VolLiter),
+        Token::VolM => Some(Unit::VolM),
+        Token::VolInch => Some(Unit::VolInch),
+        Token::VolFeet => Some(Unit::VolFeet),
+        Token::VolYard => Some(Unit::VolYard),
+        Token::VolPint => Some(unit::regional(Unit::VolPintUs, Unit::VolPintImp)),
+        Token::VolGallon => Some(unit::regional(Unit::VolGallonUs, Unit::VolGallonImp)),
+        Token::VolCup => Some(unit::regional(Unit::VolCupUs, Unit::VolCupMetric)),
+        Token::VolFlOz => Some(unit::regional(Unit::VolFlOzUs, Unit::VolFlOzImp)),
+        Token::VolTbsp => Some(unit::regional(Unit::VolTbspUs, Unit::VolTbspMetric)),
+        Token::VolTsp => Some(unit::regional(Unit::VolTspUs, Unit::VolTspMetric)),
+        Token::VolGallonUs => Some(Unit::VolGallonUs),
+        Token::VolGallonImp => Some(Unit::VolGallonImp),
+        Token::VolPintUs => Some(Unit::VolPintUs),
+        Token::VolPintImp => Some(Unit::VolPintImp),
+        Token::VolCupUs => Some(Unit::VolCupUs),
+        Token::VolCupMetric => Some(Unit::VolCupMetric),
+        Token::VolFlOzUs => Some(Unit::VolFlOzUs),
+        Token::VolFlOzImp => Some(Unit::VolFlOzImp),
+        Token::VolTbspUs => Some(Unit::VolTbspUs),
+        Token::VolTbspMetric => Some(Unit::VolTbspMetric),
+        Token::VolTspUs => Some(Unit::VolTspUs),
+        Token::VolTspMetric => Some(Unit::VolTspMetric),
+        Token::MassG => Some(Unit::MassG),
+        Token::MassOunce => Some(Unit::MassOunce),
+        Token::MassPound => Some(Unit::MassPound),
+        Token::MassTonne => Some(Unit::MassTonne),
+        Token::MassTon => Some(unit::regional(Unit::MassShortTon, Unit::MassLongTon)),
+        Token::MassShortTon => Some(Unit::MassShortTon),
+        Token::MassLongTon => Some(Unit::MassLongTon),
+        Token::MassStone => Some(Unit::MassStone),
+        Token::MassTroyOunce => Some(Unit::MassTroyOunce),
+        Token::MassCarat => Some(Unit::MassCarat),
+        Token::MassGrain => Some(Unit::MassGrain),
+        Token::SpeedMph => Some(Unit::SpeedMph),
+        Token::SpeedKnot => Some(Unit::SpeedKnot),
+        Token::DataBit => Some(Unit::DataBit),
+        Token::DataByte => Some(Unit::DataByte),
+        Token::EnergyJoule => Some(Unit::EnergyJoule),
+        Token::EnergyWh => Some(Unit::EnergyWh),
+        Token::EnergyCal => Some(Unit::EnergyCal),
+        Token::EnergyBtu => Some(Unit::EnergyBtu),
+        Token::PowerWatt => Some(Unit::PowerWatt),
+        Token::PowerHp => Some(Unit::PowerHp),
+        Token::PressurePa => Some(Unit::PressurePa),
+        Token::PressureBar => Some(Unit::PressureBar),
+        Token::PressurePsi => Some(Unit::PressurePsi),
+        Token::PressureAtm => Some(Unit::PressureAtm),
+        Token::PressureMmHg => Some(Unit::PressureMmHg),
+        Token::FuelL100km => Some(Unit::FuelL100km),
+        Token::FuelMpg => Some(Unit::FuelMpg),
+        Token::FuelMpgImp => Some(Unit::FuelMpgImp),
+        Token::FreqHz => Some(Unit::FreqHz),
+        Token::FreqRpm => Some(Unit::FreqRpm),
+        Token::FreqBpm => Some(Unit::FreqBpm),
+        Token::ElecVolt => Some(Unit::ElecVolt),
+        Token::ElecAmpere => Some(Unit::ElecAmpere),
+        Token::ElecOhm => Some(Unit::ElecOhm),
+        Token::ElecFarad => Some(Unit::ElecFarad),
+        Token::ElecHenry => Some(Unit::ElecHenry),
+        Token::ElecCoulomb => Some(Unit::ElecCoulomb),
+        Token::TempC => Some(Unit::TempC),
+        Token::TempF => Some(Unit::TempF),
+        Token::TempK => Some(Unit::TempK),
+        Token::TempR => Some(Unit::TempR),
+        Token::TempDeltaC => Some(Unit::TempDeltaC),
+        Token::TempDeltaF => Some(Unit::TempDeltaF),
+        Token::TempDeltaK => Some(Unit::TempDeltaK),
+        Token::TempDeltaR => Some(Unit::TempDeltaR),
+        Token::TimeHour => Some(Unit::TimeHour),
+        Token::TimeMin => Some(Unit::TimeMin),
+        Token::TimeDay => Some(Unit::TimeDay),
+        Token::TimeWeek => Some(Unit::TimeWeek),
+        Token::TimeFortnight => Some(Unit::TimeFortnight),
+        Token::TimeMonth => Some(Unit::TimeMonth),
+        Token::TimeYear => Some(Unit::TimeYear),
+        Token::AngleDeg => Some(Unit::AngleDeg),
+        Token::AngleRad => Some(Unit::AngleRad),
+        Token::AngleGrad => Some(Unit::AngleGrad),
+        Token::AngleTurn => Some(Unit::AngleTurn),
+        Token::AngleArcmin => Some(Unit::AngleArcmin),
+        Token::AngleArcsec => Some(Unit::AngleArcsec),
+        Token::TimeSec => Some(Unit::TimeSec),
+        Token::Prefixed(prefix, base) => token_unit(base).map(|base| unit::prefixed(*prefix, base)),
+        Token::Ident(name) => unit::custom(name),
+        Token::Curr(name) => {
+            if let Ok(idx) = CURRENCIES.binary_search_by(|p: &&str| (*p).cmp(name.as_str())) {
+                Some(Unit::Curr(CURRENCIES[idx]))
+            } else {
+                None
+            }
+        }
+        _ => None,
+    }
+}
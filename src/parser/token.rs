@@ -1,6 +1,11 @@
+use num_bigint::BigInt;
 use strum::EnumCount;
 
-#[derive(PartialEq, EnumCount)]
+use crate::rational::Rational;
+use crate::unit::Prefix;
+
+#[derive(Clone, PartialEq, EnumCount)]
+#[allow(clippy::upper_case_acronyms)]
 pub enum Token {
     ParBegin, // (
     ParEnd,   // )
@@ -9,74 +14,169 @@ pub enum Token {
     Add,      // +
     Mul,      // *
     Div,      // /
+    IntDiv,   // //
     Mod,      // %
+    Fact,     // !
+    Parallel, // ||
     KwTo,     // to
     KwFixed,  // fixed (formatter)
     KwFloat,  // float (formatter)
     KwSci,    // sci (formatter)
     KwFin,    // fin, financial (formatter)
     KwRat,    // rat, rational (formatter)
+    KwRep,    // rep, repeating (formatter)
+    KwDms,    // dms (formatter)
     KwAns,    // ans
 
-    LitFloat(f64), // float
-    LitInt(i64),   // int
+    LitDec(Rational), // decimal, exact
+    LitInt(BigInt),   // int
+    LitDms(Rational), // 12°34'56", in degrees
 
     Curr(String), // currency
 
-    LenM,    // m
-    LenKm,   // km
-    LenCm,   // cm
-    LenMm,   // mm
-    LenInch, // in, inch, "
-    LenFeet, // ft, feet, '
-    LenYard, // yd, yard
-    LenMile, // mi
-
-    AreaM,    // m2
-    AreaKm,   // km2
-    AreaCm,   // cm2
-    AreaMm,   // mm2
-    AreaInch, // in2
-    AreaFeet, // ft2
-    AreaYard, // yd2
-    AreaMile, // mi2
-
-    VolLiter,      // l, liter
-    VolM,          // m3
-    VolCm,         // cm3
-    VolMm,         // mm3
-    VolInch,       // in3
-    VolFeet,       // ft3
-    VolYard,       // yd3
-    VolMilliLiter, // ml
-    VolPint,       // pt, pint
-    VolGallon,     // gal, gallon
-    VolCup,        // cup
-
-    MassG,     // g
-    MassKg,    // kg
-    MassOunce, // ounce, oz
-    MassPound, // pound, lb
+    Prefixed(Prefix, Box<Token>), // km, µs, mg, dl, ...
+
+    LenM,            // m
+    LenInch,         // in, inch, "
+    LenFeet,         // ft, feet, '
+    LenYard,         // yd, yard
+    LenMile,         // mi
+    LenNauticalMile, // nmi
+    LenAngstrom,     // Å, angstrom
+    LenAu,           // AU
+    LenLightYear,    // ly, lightyear
+    LenParsec,       // pc, parsec (takes SI prefixes: kpc, Mpc)
+    LenFurlong,      // furlong
+    LenChain,        // ch, chain
+    LenPoint,        // pts
+    LenPica,         // pica, picas
+    LenPixel,        // px
+    LenEm,           // em
+
+    AreaM,          // m2
+    AreaInch,       // in2
+    AreaFeet,       // ft2
+    AreaYard,       // yd2
+    AreaMile,       // mi2
+    AreaAre,        // are, ares
+    AreaHectare,    // ha
+    AreaAcre,       // acre, acres, ac
+    AreaSquareRod,  // sqrod
+    AreaNegyszogol, // négyszögöl, negyszogol, nöl
+    AreaHold,       // hold, kh
+
+    VolLiter,  // l, L, liter
+    VolM,      // m3
+    VolInch,   // in3
+    VolFeet,   // ft3
+    VolYard,   // yd3
+    VolPint,   // pt, pint
+    VolGallon, // gal, gallon
+    VolCup,    // cup
+    VolFlOz,   // fl oz, floz
+    VolTbsp,   // tbsp
+    VolTsp,    // tsp
+
+    VolGallonUs,   // usgal
+    VolGallonImp,  // impgal
+    VolPintUs,     // uspt, uspint
+    VolPintImp,    // imppt, imppint
+    VolCupUs,      // uscup
+    VolCupMetric,  // mcup
+    VolFlOzUs,     // usfloz
+    VolFlOzImp,    // impfloz
+    VolTbspUs,     // ustbsp
+    VolTbspMetric, // mtbsp
+    VolTspUs,      // ustsp
+    VolTspMetric,  // mtsp
+
+    MassG,         // g
+    MassOunce,     // ounce, oz
+    MassPound,     // pound, lb
+    MassTonne,     // t, tonne, tonnes
+    MassTon,       // ton, tons (short or long, by region)
+    MassShortTon,  // shortton, short_ton
+    MassLongTon,   // longton, long_ton
+    MassStone,     // st, stone
+    MassTroyOunce, // ozt
+    MassCarat,     // ct, carat
+    MassGrain,     // gr, grain
+
+    SpeedMph,  // mph
+    SpeedKnot, // kn, knot, knots
+
+    DataBit,  // bit, bits (with decimal or binary prefix)
+    DataByte, // B, byte, bytes (with decimal or binary prefix)
+
+    EnergyJoule, // J
+    EnergyWh,    // Wh
+    EnergyCal,   // cal
+    EnergyBtu,   // BTU, Btu, btu
+
+    PowerWatt, // W
+    PowerHp,   // hp
+
+    PressurePa,   // Pa
+    PressureBar,  // bar
+    PressurePsi,  // psi
+    PressureAtm,  // atm
+    PressureMmHg, // mmHg
+
+    FuelL100km, // L/100km, l/100km
+    FuelMpg,    // mpg
+    FuelMpgImp, // mpg_imp
+
+    FreqHz,  // Hz (with SI prefix: kHz, MHz, GHz)
+    FreqRpm, // rpm
+    FreqBpm, // bpm
+
+    ElecVolt,    // V, volt, volts
+    ElecAmpere,  // A, amp, ampere, amperes
+    ElecOhm,     // Ω, ohm, ohms
+    ElecFarad,   // farad, farads (F only after a prefix)
+    ElecHenry,   // H, henry, henries
+    ElecCoulomb, // coulomb, coulombs (C only after a prefix)
 
     TempC, // c
     TempF, // f
+    TempK, // K, kelvin
+    TempR, // R, rankine
+
+    TempDeltaC, // ΔC, dC
+    TempDeltaF, // ΔF, dF
+    TempDeltaK, // ΔK, dK
+    TempDeltaR, // ΔR, dR
     //
-    TimeSec,  // s, sec
-    TimeMin,  // min
-    TimeHour, // hour, hr
+    TimeSec,       // s, sec, second(s)
+    TimeMin,       // min, minute(s)
+    TimeHour,      // h, hr, hour(s)
+    TimeDay,       // d, day, days
+    TimeWeek,      // wk, week, weeks
+    TimeFortnight, // fortnight, fortnights
+    TimeMonth,     // mo, month, months
+    TimeYear,      // yr, year, years
+
+    AngleDeg,    // deg, degree, °
+    AngleRad,    // rad, radian
+    AngleGrad,   // grad, gradian
+    AngleTurn,   // turn
+    AngleArcmin, // arcmin
+    AngleArcsec, // arcsec
 
-    Pipe, // |
+    Pipe,   // |
+    Assign, // =
+    Comma,  // ,
 
     Ident(String),
 
     INVALID(String),
 }
 
-pub const CURRENCIES: [&'static str; 34] = [
+pub const CURRENCIES: [&str; 34] = [
     "AUD", "BGN", "BRL", "CAD", "CHF", "CNY", "CZK", "DKK", "EUR", "GBP", "HKD", "HUF", "IDR",
     "ILS", "INR", "ISK", "JPY", "KRW", "MXN", "MYR", "NOK", "NZD", "PHP", "PLN", "RON", "RSD",
     "RUB", "SEK", "SGD", "THB", "TRY", "UAH", "USD", "ZAR",
 ];
 
 // TODO: this should come from a macro
-pub const CURRENCIES_PATTERN: &'static str = "AUD|aud|BGN|bgn|BRL|brl|CAD|cad|CHF|chf|CNY|cny|CZK|czk|DKK|dkk|EUR|eur|GBP|gbp|HKD|hkd|HUF|huf|IDR|idr|ILS|ils|INR|inr|ISK|isk|JPY|jpy|KRW|krw|MXN|mxn|MYR|myr|NOK|nok|NZD|nzd|PHP|php|PLN|pln|RON|ron|RSD|rsd|RUB|rub|SEK|sek|SGD|sgd|THB|thb|TRY|try|UAH|uah|USD|usd|ZAR|zar";
+pub const CURRENCIES_PATTERN: &str = "AUD|aud|BGN|bgn|BRL|brl|CAD|cad|CHF|chf|CNY|cny|CZK|czk|DKK|dkk|EUR|eur|GBP|gbp|HKD|hkd|HUF|huf|IDR|idr|ILS|ils|INR|inr|ISK|isk|JPY|jpy|KRW|krw|MXN|mxn|MYR|myr|NOK|nok|NZD|nzd|PHP|php|PLN|pln|RON|ron|RSD|rsd|RUB|rub|SEK|sek|SGD|sgd|THB|thb|TRY|try|UAH|uah|USD|usd|ZAR|zar";
@@ -1,12 +1,19 @@
 use std::fmt::{Display, Formatter};
 use std::ops;
+use std::str::FromStr;
+
+use num_bigint::BigInt;
+use num_integer::Integer;
+use num_traits::float::FloatCore;
+use num_traits::{One, Signed, ToPrimitive, Zero};
 
 use crate::error::CalcError;
 
+/// Exact fraction in lowest terms. `den` is always positive; the sign lives in `num`.
 #[derive(Clone, Debug)]
 pub struct Rational {
-    pub num: i64,
-    pub den: u64,
+    pub num: BigInt,
+    pub den: BigInt,
 }
 
 impl Display for Rational {
@@ -16,91 +23,171 @@ impl Display for Rational {
 }
 
 impl Rational {
-    pub const fn new(num: i64, den: u64) -> Self {
-        if den == 0 {
+    pub fn new(num: i64, den: u64) -> Self {
+        Rational::new_big(num.into(), den.into())
+    }
+
+    pub fn new_big(mut num: BigInt, mut den: BigInt) -> Self {
+        if den.is_zero() {
             panic!("Cannot divide by zero")
         }
-        let _gcd = gcd(num.unsigned_abs(), den);
-        Rational {
-            num: num / (_gcd as i64),
-            den: den / _gcd,
+        if den.is_negative() {
+            num = -num;
+            den = -den;
+        }
+        let gcd = num.gcd(&den);
+        if !gcd.is_one() {
+            num /= &gcd;
+            den /= &gcd;
         }
+        Rational { num, den }
     }
 
-    pub fn inverse(value: i64) -> Result<Rational, CalcError> {
-        if value == 0 {
-            return Err(CalcError::DivByZero);
-        }
-        Ok(Rational {
-            num: value.signum(),
-            den: value.unsigned_abs(),
-        })
+    pub fn is_integer(&self) -> bool {
+        self.den.is_one()
     }
 
     pub fn invert(&self) -> Result<Rational, CalcError> {
-        if self.num == 0 {
+        if self.num.is_zero() {
             return Err(CalcError::DivByZero);
         }
-        let num = self.num.signum() * (self.den as i64);
-        let den = self.num.unsigned_abs();
-        Ok(Rational { num, den })
+        Ok(Rational::new_big(self.den.clone(), self.num.clone()))
     }
 
-    pub fn checked_pow(&self, value: i32) -> Option<Rational> {
-        let abs = value.unsigned_abs();
-        if let Some(num) = self.num.checked_pow(abs) {
-            if let Some(den) = self.den.checked_pow(abs) {
-                if value >= 0 {
-                    return Some(Rational { num, den });
-                } else {
-                    if let Ok(den) = i64::try_from(den) {
-                        return Some(Rational {
-                            num: num.signum() * den,
-                            den: num.unsigned_abs(),
-                        });
-                    }
-                }
-            }
-        }
-        None
+    /// `self^value`; panics for `0^-n`, so only use it on known non-zero bases
+    /// (unit factors). Evaluation goes through `checked_pow`.
+    pub fn pow(&self, value: i32) -> Rational {
+        self.checked_pow(value)
+            .expect("Cannot raise zero to a negative power")
     }
 
-    pub const fn pow(&self, value: i32) -> Rational {
+    pub fn checked_pow(&self, value: i32) -> Result<Rational, CalcError> {
         let abs = value.unsigned_abs();
-        let num = self.num.pow(abs);
-        let den = self.den.pow(abs);
+        let res = Rational {
+            num: self.num.pow(abs),
+            den: self.den.pow(abs),
+        };
         if value >= 0 {
-            Rational { num, den }
+            Ok(res)
         } else {
-            Rational {
-                num: num.signum() * (den as i64),
-                den: num.unsigned_abs(),
-            }
+            res.invert()
+        }
+    }
+
+    pub fn checked_div(&self, rhs: &Rational) -> Result<Rational, CalcError> {
+        Ok(self * &rhs.invert()?)
+    }
+
+    /// The exact value of a finite float (every `f64` is a binary fraction).
+    pub fn from_f64(x: f64) -> Option<Rational> {
+        if !x.is_finite() {
+            return None;
+        }
+        let (mantissa, exp, sign) = x.integer_decode();
+        let num = BigInt::from(mantissa) * sign;
+        Some(if exp >= 0 {
+            Rational::from(num << exp as u32)
+        } else {
+            Rational::new_big(num, BigInt::one() << (-exp) as u32)
+        })
+    }
+
+    /// Exact `n`-th root, or `None` when the result is irrational (or not real).
+    pub fn root(&self, n: u32) -> Option<Rational> {
+        if n == 0 || (self.num.is_negative() && n.is_multiple_of(2)) {
+            return None;
+        }
+        let num = self.num.nth_root(n);
+        let den = self.den.nth_root(n);
+        if num.pow(n) == self.num && den.pow(n) == self.den {
+            Some(Rational { num, den })
+        } else {
+            None
         }
     }
 }
 
+/// Largest decimal exponent parsed exactly; `1e999999999` would otherwise
+/// allocate a billion-digit integer.
+const MAX_DECIMAL_EXP: i64 = 1000;
+
+/// Parses a decimal literal (`1.25`, `.5`, `2.`, `1.5e-3`) exactly.
+impl FromStr for Rational {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (mantissa, exp) = match s.find(['e', 'E']) {
+            Some(idx) => (&s[..idx], s[idx + 1..].parse::<i64>().map_err(|_| ())?),
+            None => (s, 0),
+        };
+        let (int, frac) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+        let digits = format!("{int}{frac}");
+        if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+            return Err(());
+        }
+        let exp = exp - frac.len() as i64;
+        if exp.abs() > MAX_DECIMAL_EXP {
+            return Err(());
+        }
+        let num: BigInt = digits.parse().map_err(|_| ())?;
+        let scale = BigInt::from(10).pow(exp.unsigned_abs() as u32);
+        Ok(if exp >= 0 {
+            Rational::from(num * scale)
+        } else {
+            Rational::new_big(num, scale)
+        })
+    }
+}
+
 impl From<i64> for Rational {
     fn from(value: i64) -> Self {
-        Rational { num: value, den: 1 }
+        BigInt::from(value).into()
     }
 }
 
-impl Into<f64> for &Rational {
-    fn into(self) -> f64 {
-        (self.num as f64) / (self.den as f64)
+impl From<BigInt> for Rational {
+    fn from(value: BigInt) -> Self {
+        Rational {
+            num: value,
+            den: BigInt::one(),
+        }
     }
 }
 
-impl Into<f64> for Rational {
-    fn into(self) -> f64 {
-        (self.num as f64) / (self.den as f64)
+impl From<&Rational> for f64 {
+    fn from(val: &Rational) -> Self {
+        if let (Some(num), Some(den)) = (val.num.to_f64(), val.den.to_f64()) {
+            if num.abs() < 2f64.powi(53) && den < 2f64.powi(53) {
+                return num / den;
+            }
+        }
+        // Scale the quotient to ~64 significant bits so huge numerators and
+        // denominators don't turn into inf / inf.
+        let shift = val.den.bits() as i64 - val.num.bits() as i64 + 64;
+        let quot = if shift >= 0 {
+            (&val.num << shift as u64) / &val.den
+        } else {
+            &val.num / (&val.den << (-shift) as u64)
+        };
+        let quot = quot.to_f64().unwrap_or(f64::NAN);
+        let shift = shift.clamp(i32::MIN as i64, i32::MAX as i64) as i32;
+        if shift > 1000 {
+            quot * 2f64.powi(-1000) * 2f64.powi(1000 - shift)
+        } else {
+            quot * 2f64.powi(-shift)
+        }
+    }
+}
+
+impl From<Rational> for f64 {
+    fn from(val: Rational) -> Self {
+        (&val).into()
     }
 }
 
 impl PartialEq<i64> for Rational {
     fn eq(&self, other: &i64) -> bool {
-        self.den == 1 && self.num == *other
+        self.den.is_one() && self.num == BigInt::from(*other)
     }
 }
 
@@ -110,31 +197,31 @@ impl PartialEq<Rational> for Rational {
     }
 }
 
-const fn gcd(mut a: u64, mut b: u64) -> u64 {
-    let mut r = a % b;
-    while r > 0 {
-        a = b % r;
-        b = r;
-        r = a;
-    }
-    b
-}
+impl ops::Add<&Rational> for &Rational {
+    type Output = Rational;
 
-fn lcm(a: u64, b: u64) -> u64 {
-    a * b / gcd(a, b)
+    fn add(self, rhs: &Rational) -> Self::Output {
+        let den = self.den.lcm(&rhs.den);
+        let num = &self.num * (&den / &self.den) + &rhs.num * (&den / &rhs.den);
+        Rational::new_big(num, den)
+    }
 }
 
 impl ops::Add<Rational> for Rational {
     type Output = Rational;
 
     fn add(self, rhs: Rational) -> Self::Output {
-        let den = lcm(self.den, rhs.den);
-        let num = self.num * ((den / self.den) as i64) + rhs.num * ((den / rhs.den) as i64);
-        let _gcd = gcd(num.unsigned_abs(), den);
-        Rational {
-            num: num / (_gcd as i64),
-            den: den / _gcd,
-        }
+        &self + &rhs
+    }
+}
+
+impl ops::Sub<&Rational> for &Rational {
+    type Output = Rational;
+
+    fn sub(self, rhs: &Rational) -> Self::Output {
+        let den = self.den.lcm(&rhs.den);
+        let num = &self.num * (&den / &self.den) - &rhs.num * (&den / &rhs.den);
+        Rational::new_big(num, den)
     }
 }
 
@@ -142,13 +229,7 @@ impl ops::Sub<Rational> for Rational {
     type Output = Rational;
 
     fn sub(self, rhs: Rational) -> Self::Output {
-        let den = lcm(self.den, rhs.den);
-        let num = self.num * ((den / self.den) as i64) - rhs.num * ((den / rhs.den) as i64);
-        let _gcd = gcd(num.unsigned_abs(), den);
-        Rational {
-            num: num / (_gcd as i64),
-            den: den / _gcd,
-        }
+        &self - &rhs
     }
 }
 
@@ -163,31 +244,41 @@ impl ops::Neg for Rational {
     }
 }
 
-impl ops::Mul<Rational> for Rational {
+impl ops::Mul<&Rational> for &Rational {
     type Output = Rational;
 
-    fn mul(self, rhs: Rational) -> Self::Output {
-        let num = self.num * rhs.num;
-        let den = self.den * rhs.den;
-        let _gcd = gcd(num.unsigned_abs(), den);
+    fn mul(self, rhs: &Rational) -> Self::Output {
+        // Cancel crosswise before multiplying to keep the intermediate products small.
+        let gcd_a = self.num.gcd(&rhs.den);
+        let gcd_b = rhs.num.gcd(&self.den);
         Rational {
-            num: num / (_gcd as i64),
-            den: den / _gcd,
+            num: (&self.num / &gcd_a) * (&rhs.num / &gcd_b),
+            den: (&self.den / &gcd_b) * (&rhs.den / &gcd_a),
         }
     }
 }
 
+impl ops::Mul<Rational> for Rational {
+    type Output = Rational;
+
+    fn mul(self, rhs: Rational) -> Self::Output {
+        &self * &rhs
+    }
+}
+
+impl ops::Div<&Rational> for &Rational {
+    type Output = Rational;
+
+    fn div(self, rhs: &Rational) -> Self::Output {
+        self.checked_div(rhs).expect("Cannot divide by zero")
+    }
+}
+
 impl ops::Div<Rational> for Rational {
     type Output = Rational;
 
     fn div(self, rhs: Rational) -> Self::Output {
-        let num = self.num * rhs.num.signum() * (rhs.den as i64);
-        let den = self.den * rhs.num.unsigned_abs();
-        let _gcd = gcd(num.unsigned_abs(), den);
-        Rational {
-            num: num / (_gcd as i64),
-            den: den / _gcd,
-        }
+        &self / &rhs
     }
 }
 
@@ -207,9 +298,72 @@ mod tests {
         assert_eq!(result, Rational::new(0, 1));
     }
 
+    #[test]
+    fn test_mul_cancels_crosswise() {
+        let result =
+            Rational::new(473176473, 1000000000000) / Rational::new(473176473, 125000000000);
+        assert_eq!(result, Rational::new(1, 8));
+    }
+
     #[test]
     fn test_mul_to_zero() {
         let result = Rational::new(1, 2) * Rational::new(0, 1);
         assert_eq!(result, Rational::new(0, 1));
     }
+
+    #[test]
+    fn test_add_beyond_i64() {
+        let big = Rational::new(1, 3).pow(40);
+        let result = &big + &big;
+        assert_eq!(result.num, BigInt::from(2));
+        assert_eq!(result.den, BigInt::from(3).pow(40));
+    }
+
+    #[test]
+    fn test_from_str() {
+        assert_eq!("0.1".parse(), Ok(Rational::new(1, 10)));
+        assert_eq!(".5".parse(), Ok(Rational::new(1, 2)));
+        assert_eq!("2.".parse(), Ok(Rational::from(2)));
+        assert_eq!("1.5e-3".parse(), Ok(Rational::new(3, 2000)));
+        assert_eq!("2.5E2".parse(), Ok(Rational::from(250)));
+        assert_eq!("1e9999".parse::<Rational>(), Err(()));
+    }
+
+    #[test]
+    fn test_from_f64_is_exact() {
+        assert_eq!(Rational::from_f64(0.375), Some(Rational::new(3, 8)));
+        assert_eq!(Rational::from_f64(-4.0), Some(Rational::from(-4)));
+        assert_eq!(
+            Rational::from_f64(0.1),
+            Some(Rational::new(3602879701896397, 36028797018963968))
+        );
+        assert_eq!(Rational::from_f64(f64::INFINITY), None);
+    }
+
+    #[test]
+    fn test_checked_ops_reject_zero() {
+        let zero = Rational::from(0);
+        assert_eq!(
+            Rational::new(2, 3).checked_pow(-2).unwrap(),
+            Rational::new(9, 4)
+        );
+        assert!(matches!(zero.checked_pow(-1), Err(CalcError::DivByZero)));
+        assert!(matches!(
+            Rational::from(1).checked_div(&zero),
+            Err(CalcError::DivByZero)
+        ));
+    }
+
+    #[test]
+    fn test_root() {
+        assert_eq!(Rational::new(9, 4).root(2), Some(Rational::new(3, 2)));
+        assert_eq!(Rational::new(-8, 27).root(3), Some(Rational::new(-2, 3)));
+        assert_eq!(Rational::new(2, 1).root(2), None);
+    }
+
+    #[test]
+    fn test_to_f64_with_huge_parts() {
+        let r = Rational::new_big(BigInt::from(10).pow(400) + 1, BigInt::from(10).pow(400) * 4);
+        assert_eq!(f64::from(r), 0.25);
+    }
 }
@@ -9,9 +9,11 @@ use rustyline::{CompletionType, Config as RlConfig, Context, Editor, Helper};
 use crate::config::{self, FormatSpec};
 use crate::error::CalcError;
 use crate::files;
-use crate::node::Node;
+use crate::node::{Node, Statement};
 use crate::parser::lexer::Lexer;
 use crate::parser::parser::Parser;
+use crate::parser::token::Token;
+use crate::unit::{self, CustomDef};
 use crate::value::{format_value, Value};
 
 fn has_children(candidate: &str) -> bool {
@@ -32,8 +34,8 @@ fn next_level_completion<'a>(key: &'a str, prefix: &str) -> &'a str {
     if suffix.is_empty() {
         return key;
     }
-    let (search, base) = if suffix.starts_with('.') {
-        (&suffix[1..], prefix.len() + 1)
+    let (search, base) = if let Some(rest) = suffix.strip_prefix('.') {
+        (rest, prefix.len() + 1)
     } else {
         (suffix, prefix.len())
     };
@@ -58,7 +60,7 @@ impl Completer for ReplHelper {
 
         // Complete the command name itself: "/con" → "/config "
         if before.starts_with('/') && !before.contains(' ') {
-            let candidates = ["/config"]
+            let candidates = ["/config", "/vars", "/funcs", "/unset"]
                 .iter()
                 .filter(|cmd| cmd.starts_with(before))
                 .map(|cmd| Pair {
@@ -149,7 +151,7 @@ impl Highlighter for ReplHelper {}
 impl Validator for ReplHelper {}
 impl Helper for ReplHelper {}
 
-fn handle_meta_command(line: &str) {
+fn handle_meta_command(line: &str, parser: &mut Parser) {
     let mut parts = line.splitn(2, ' ');
     let cmd = parts.next().unwrap_or("");
     let rest = parts.next().map(str::trim);
@@ -202,12 +204,59 @@ fn handle_meta_command(line: &str) {
                 }
             }
         },
-        other => println!("unknown command {other:?}; available: /config"),
+        "/vars" => {
+            let opts = config::current().format.clone();
+            for (name, val) in parser.vars() {
+                println!("{name} = {}", format_value(val, &opts));
+            }
+        }
+        "/funcs" => {
+            for (_, func) in parser.funcs() {
+                println!("{}", func.text);
+            }
+        }
+        "/unset" => match rest {
+            None | Some("") => println!("usage: /unset <name>"),
+            Some(name) => {
+                let var = parser.unset_var(name);
+                let func = parser.undefine(name);
+                if var.is_none() && func.is_none() {
+                    println!("unknown variable or function {name:?}");
+                }
+            }
+        },
+        other => {
+            println!("unknown command {other:?}; available: /config, /vars, /funcs, /unset")
+        }
+    }
+}
+
+/// Run a parsed statement: print its result (or the error), store assigned
+/// variables and defined functions, and record successful results as `ans`.
+/// `source` is the statement as typed, kept as the text of a definition.
+fn execute(statement: Statement, spec: &Option<FormatSpec>, parser: &mut Parser, source: &str) {
+    match statement {
+        Statement::Expr(node) => {
+            if let Some(res) = evaluate_and_print(node, spec) {
+                parser.set_ans(res);
+            }
+        }
+        Statement::Assign(name, node) => {
+            if let Some(res) = evaluate_and_print(node, spec) {
+                parser.set_var(name, res.clone());
+                parser.set_ans(res);
+            }
+        }
+        Statement::Define(name, mut func) => {
+            func.text = source.trim().to_string();
+            parser.define(name, func);
+        }
+        Statement::Invalid(error) => println!("{error}"),
     }
 }
 
 /// Evaluate a parsed node, print its formatted result (or the error), and
-/// return the value on success so callers can record it as `ans`.
+/// return the value on success.
 fn evaluate_and_print(node: Node, spec: &Option<FormatSpec>) -> Option<Value> {
     match node.eval() {
         Ok(res) => {
@@ -249,20 +298,18 @@ pub fn run() -> Result<(), CalcError> {
         match rl.readline(if parser.is_empty() { ">> " } else { ".. " }) {
             Ok(line) => {
                 if line.trim_start().starts_with('/') {
-                    handle_meta_command(line.trim());
+                    handle_meta_command(line.trim(), &mut parser);
                     let _ = rl.add_history_entry(&line);
                     continue;
                 }
                 if !line_buffer.is_empty() {
-                    line_buffer.push_str(" ");
+                    line_buffer.push(' ');
                 }
                 line_buffer.push_str(line.as_str());
                 parser.extend(lexer.parse(line.as_str()));
 
-                if let Some((node, spec)) = parser.parse() {
-                    if let Some(res) = evaluate_and_print(node, &spec) {
-                        parser.set_ans(res);
-                    }
+                if let Some((statement, spec)) = parser.parse() {
+                    execute(statement, &spec, &mut parser, &line_buffer);
                     let _ = rl.add_history_entry(line_buffer.as_str());
                     line_buffer.clear();
                     parser.reset();
@@ -286,13 +333,51 @@ pub fn run() -> Result<(), CalcError> {
     Ok(())
 }
 
-/// One-shot evaluation of a single expression string (CLI argument mode).
+/// Evaluates the `[units.custom]` definitions with the calculator itself and
+/// registers the results, so `5 pallet` parses like a built-in unit. A
+/// definition may only use built-in units; an empty one declares a base
+/// quantity.
+pub fn init_custom_units() -> Result<(), CalcError> {
+    let mut custom: Vec<(String, String)> =
+        config::current().units.custom.clone().into_iter().collect();
+    custom.sort();
+    let lexer = Lexer::new();
+    let mut defs = Vec::with_capacity(custom.len());
+    for (name, text) in custom {
+        let invalid =
+            |reason: String| CalcError::ConfigError(format!("custom unit {name:?}: {reason}"));
+        // A name that doesn't lex as a plain identifier would never reach the
+        // parser as this unit (`pt`, `to`, `2x`).
+        if lexer.parse(&name).collect::<Vec<_>>() != [Token::Ident(name.clone())] {
+            return Err(invalid("not a free identifier".to_string()));
+        }
+        let def = if text.trim().is_empty() {
+            CustomDef::Base
+        } else {
+            let mut parser = Parser::new();
+            parser.extend(lexer.parse(&text));
+            let Some((Statement::Expr(node), None)) = parser.parse() else {
+                return Err(invalid(format!("can't parse {text:?}")));
+            };
+            let value = node.eval().map_err(|e| invalid(e.to_string()))?;
+            CustomDef::Quantity(value.num, value.unit)
+        };
+        defs.push((name, def));
+    }
+    unit::register_custom(defs)
+}
+
+/// One-shot evaluation of the CLI argument. `;` separates statements, so
+/// variables can be set up first: `rate = 392.5 HUF; 20 EUR to HUF / rate`.
 pub fn run_once(input: &str) -> Result<(), CalcError> {
     let lexer = Lexer::new();
     let mut parser = Parser::new();
-    parser.extend(lexer.parse(input));
-    if let Some((node, spec)) = parser.parse() {
-        evaluate_and_print(node, &spec);
+    for part in input.split(';') {
+        parser.extend(lexer.parse(part));
+        if let Some((statement, spec)) = parser.parse() {
+            execute(statement, &spec, &mut parser, part);
+        }
+        parser.reset();
     }
     Ok(())
 }
@@ -1,3 +1,6 @@
+use std::sync::OnceLock;
+
+use crate::config::{self, Region};
 use crate::error::CalcError;
 use crate::number::Number;
 use crate::rational::Rational;
@@ -10,209 +13,939 @@ pub enum UnitType {
     Volume,
     Mass,
     Temperature,
+    TemperatureInterval,
+    Time,
+    Speed,
+    Energy,
+    Power,
+    Pressure,
+    FuelConsumption,
+    FuelEconomy,
+    Frequency,
+    Voltage,
+    Current,
+    Resistance,
+    Capacitance,
+    Inductance,
+    Charge,
+    Data,
+    Currency,
+    Angle,
+    Custom,
+    Derived,
+}
+
+/// Base quantities that every unit's dimension is built from.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum BaseDim {
+    Length,
+    Mass,
     Time,
+    Temperature,
     Currency,
+    Angle,
+    Data,
+    Current,
+    /// A custom unit declared as a base quantity, by its registry index.
+    Custom(usize),
+}
+
+/// A dimension vector: base quantities with their (non-zero) exponents, kept
+/// sorted by base so that equal dimensions compare equal. Two units can be
+/// converted into each other exactly when their dimensions match.
+#[derive(Clone, PartialEq, Default)]
+pub struct Dim(Vec<(BaseDim, i32)>);
+
+impl Dim {
+    fn of(base: BaseDim, exp: i32) -> Dim {
+        Dim(vec![(base, exp)])
+    }
+
+    /// `self * other^exp`
+    fn mul(mut self, other: &Dim, exp: i32) -> Dim {
+        for (base, n) in &other.0 {
+            match self.0.binary_search_by(|(b, _)| b.cmp(base)) {
+                Ok(idx) => self.0[idx].1 += n * exp,
+                Err(idx) => self.0.insert(idx, (*base, n * exp)),
+            }
+        }
+        self.0.retain(|(_, n)| *n != 0);
+        self
+    }
+
+    fn inverse(&self) -> Dim {
+        Dim::default().mul(self, -1)
+    }
+
+    pub fn is_dimensionless(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+/// SI prefixes. Any of them combines with any metric base unit (`m`, `g`,
+/// `l`, `s`), e.g. `µm`, `Mg` or `dl`. Data units (`bit`, `B`) also take the
+/// binary prefixes, which are exact powers of two (`KiB` = 1024 B).
+#[derive(Clone, Copy, PartialEq)]
+pub enum Prefix {
+    Pico,
+    Nano,
+    Micro,
+    Milli,
+    Centi,
+    Deci,
+    Hecto,
+    Kilo,
+    Mega,
+    Giga,
+    Tera,
+    Kibi,
+    Mebi,
+    Gibi,
+    Tebi,
+}
+
+impl Prefix {
+    /// `u` is accepted for micro, as is the Greek letter mu.
+    pub fn from_symbol(symbol: &str) -> Option<Prefix> {
+        match symbol {
+            "p" => Some(Prefix::Pico),
+            "n" => Some(Prefix::Nano),
+            "µ" | "μ" | "u" => Some(Prefix::Micro),
+            "m" => Some(Prefix::Milli),
+            "c" => Some(Prefix::Centi),
+            "d" => Some(Prefix::Deci),
+            "h" => Some(Prefix::Hecto),
+            "k" => Some(Prefix::Kilo),
+            "M" => Some(Prefix::Mega),
+            "G" => Some(Prefix::Giga),
+            "T" => Some(Prefix::Tera),
+            "Ki" => Some(Prefix::Kibi),
+            "Mi" => Some(Prefix::Mebi),
+            "Gi" => Some(Prefix::Gibi),
+            "Ti" => Some(Prefix::Tebi),
+            _ => None,
+        }
+    }
+
+    pub fn symbol(self) -> &'static str {
+        match self {
+            Prefix::Pico => "p",
+            Prefix::Nano => "n",
+            Prefix::Micro => "µ",
+            Prefix::Milli => "m",
+            Prefix::Centi => "c",
+            Prefix::Deci => "d",
+            Prefix::Hecto => "h",
+            Prefix::Kilo => "k",
+            Prefix::Mega => "M",
+            Prefix::Giga => "G",
+            Prefix::Tera => "T",
+            Prefix::Kibi => "Ki",
+            Prefix::Mebi => "Mi",
+            Prefix::Gibi => "Gi",
+            Prefix::Tebi => "Ti",
+        }
+    }
+
+    fn factor(self) -> Rational {
+        match self {
+            Prefix::Pico => Rational::new(1, 1_000_000_000_000),
+            Prefix::Nano => Rational::new(1, 1_000_000_000),
+            Prefix::Micro => Rational::new(1, 1_000_000),
+            Prefix::Milli => Rational::new(1, 1_000),
+            Prefix::Centi => Rational::new(1, 100),
+            Prefix::Deci => Rational::new(1, 10),
+            Prefix::Hecto => Rational::from(100),
+            Prefix::Kilo => Rational::from(1_000),
+            Prefix::Mega => Rational::from(1_000_000),
+            Prefix::Giga => Rational::from(1_000_000_000),
+            Prefix::Tera => Rational::from(1_000_000_000_000),
+            Prefix::Kibi => Rational::from(1 << 10),
+            Prefix::Mebi => Rational::from(1 << 20),
+            Prefix::Gibi => Rational::from(1 << 30),
+            Prefix::Tebi => Rational::from(1 << 40),
+        }
+    }
 }
 
 #[derive(Clone, PartialEq)]
 pub enum Unit {
-    Curr(&'static str), // currency
-
-    LenM,    // m
-    LenKm,   // km
-    LenCm,   // cm
-    LenMm,   // mm
-    LenInch, // in, inch, "
-    LenFeet, // ft, feet, '
-    LenYard, // yd, yard
-    LenMile, // mi
-
-    AreaM,    // m2
-    AreaKm,   // km2
-    AreaCm,   // cm2
-    AreaMm,   // mm2
-    AreaInch, // in2
-    AreaFeet, // ft2
-    AreaYard, // yd2
-    AreaMile, // mi2
-
-    VolLiter,      // l, liter
-    VolMilliLiter, // ml
-    VolM,          // m3
-    VolCm,         // cm3
-    VolMm,         // mm3
-    VolInch,       // in3
-    VolFeet,       // ft3
-    VolYard,       // yd3
-    VolPint,       // pt, pint
-    VolGallon,     // gal, gallon
-    VolCup,        // cup
-
-    MassG,     // g
-    MassKg,    // kg
-    MassOunce, // ounce, oz
-    MassPound, // pound, lb
+    Curr(&'static str),   // currency
+    Custom(&'static str), // declared under `[units.custom]` (see `register_custom`)
+
+    // Product of units raised to integer powers, e.g. km/h = [(km, 1), (h, -1)].
+    // Built by `combine`/`pow`; the parts never contain named derived units
+    // (see `derived_parts`) and no unit appears twice.
+    Derived(Vec<(Unit, i32)>),
+
+    // A metric or data base unit with a prefix, e.g. km = (Kilo, LenM). Built by
+    // `prefixed`, so the base is never a named derived unit: `km2` is km^2.
+    Prefixed(Prefix, Box<Unit>),
+
+    LenM,            // m
+    LenInch,         // in, inch, "
+    LenFeet,         // ft, feet, '
+    LenYard,         // yd, yard
+    LenMile,         // mi
+    LenNauticalMile, // nmi
+    LenAngstrom,     // Å, angstrom
+    LenAu,           // AU
+    LenLightYear,    // ly, lightyear
+    LenParsec,       // pc, parsec
+    LenFurlong,      // furlong
+    LenChain,        // ch, chain
+    LenPoint,        // pts (1/72 in)
+    LenPica,         // pica (12 pts)
+    LenPixel,        // px (1/dpi in)
+    LenEm,           // em (the base font size in px)
+
+    AreaM,          // m2
+    AreaInch,       // in2
+    AreaFeet,       // ft2
+    AreaYard,       // yd2
+    AreaMile,       // mi2
+    AreaAre,        // are
+    AreaHectare,    // ha
+    AreaAcre,       // acre, ac
+    AreaSquareRod,  // sqrod
+    AreaNegyszogol, // négyszögöl, nöl (a square Vienna fathom)
+    AreaHold,       // hold, kh (katasztrális hold, 1600 négyszögöl)
+
+    VolLiter, // l, liter
+    VolM,     // m3
+    VolInch,  // in3
+    VolFeet,  // ft3
+    VolYard,  // yd3
+
+    // Kitchen and imperial volumes come in regional variants; the bare names
+    // (`gallon`, `cup`, `tsp`, ...) pick one by `units.region`, see `regional`.
+    VolGallonUs,   // usgal
+    VolGallonImp,  // impgal
+    VolPintUs,     // uspt
+    VolPintImp,    // imppt
+    VolCupUs,      // uscup
+    VolCupMetric,  // mcup
+    VolFlOzUs,     // usfloz
+    VolFlOzImp,    // impfloz
+    VolTbspUs,     // ustbsp
+    VolTbspMetric, // mtbsp
+    VolTspUs,      // ustsp
+    VolTspMetric,  // mtsp
+
+    MassG,         // g
+    MassOunce,     // ounce, oz
+    MassPound,     // pound, lb
+    MassTonne,     // t, tonne
+    MassShortTon,  // shortton
+    MassLongTon,   // longton
+    MassStone,     // st, stone
+    MassTroyOunce, // ozt
+    MassCarat,     // ct, carat
+    MassGrain,     // gr, grain
+
+    SpeedMph,  // mph
+    SpeedKnot, // kn, knot, knots
+
+    DataBit,  // bit, bits
+    DataByte, // B, byte, bytes
+
+    EnergyJoule, // J
+    EnergyWh,    // Wh
+    EnergyCal,   // cal
+    EnergyBtu,   // BTU
+
+    PowerWatt, // W
+    PowerHp,   // hp
+
+    PressurePa,   // Pa
+    PressureBar,  // bar
+    PressurePsi,  // psi
+    PressureAtm,  // atm
+    PressureMmHg, // mmHg
+
+    FuelL100km, // L/100km
+    FuelMpg,    // mpg (US gallon)
+    FuelMpgImp, // mpg_imp (imperial gallon)
+
+    FreqHz,  // Hz
+    FreqRpm, // rpm (revolutions per minute)
+    FreqBpm, // bpm (beats per minute)
+
+    ElecVolt,    // V
+    ElecAmpere,  // A
+    ElecOhm,     // Ω, ohm
+    ElecFarad,   // farad (F after a prefix)
+    ElecHenry,   // H
+    ElecCoulomb, // coulomb (C after a prefix)
 
     TempC, // c
     TempF, // f
+    TempK, // K, kelvin
+    TempR, // R, rankine
+
+    TempDeltaC, // ΔC
+    TempDeltaF, // ΔF
+    TempDeltaK, // ΔK
+    TempDeltaR, // ΔR
     //
-    TimeSec,  // s, sec
-    TimeMin,  // min
-    TimeHour, // hour, hr
+    TimeSec,       // s, sec
+    TimeMin,       // min
+    TimeHour,      // hour, hr
+    TimeDay,       // d, day
+    TimeWeek,      // wk, week
+    TimeFortnight, // fortnight
+    TimeMonth,     // mo, month (a twelfth of a Julian year)
+    TimeYear,      // yr, year (Julian, 365.25 days)
+
+    AngleDeg,    // deg, degree, °
+    AngleRad,    // rad, radian
+    AngleGrad,   // grad, gradian
+    AngleTurn,   // turn
+    AngleArcmin, // arcmin
+    AngleArcsec, // arcsec
 }
 
-const fn wrap(rate: Rational) -> Result<Number, CalcError> {
-    if rate.den == 1 {
-        Ok(Number::Int(rate.num))
-    } else {
-        Ok(Number::Rational(rate))
-    }
+fn wrap(rate: Rational) -> Result<Number, CalcError> {
+    Ok(rate.into())
 }
 
 pub fn get_default_factor(a: &Unit) -> Result<Number, CalcError> {
-    const _1: Rational = Rational::new(1, 1);
-    const _1000: Rational = Rational::new(1000, 1);
-    const _1_1000: Rational = Rational::new(1, 1000);
-    const _1_100: Rational = Rational::new(1, 100);
-    const INCH2M: Rational = Rational::new(254, 10000);
-    const FEET2INCH: Rational = Rational::new(12, 1);
-    const YARD2INCH: Rational = Rational::new(36, 1);
-    const MILE2INCH: Rational = Rational::new(63360, 1);
+    let one = Rational::from(1);
+    let milli = Rational::new(1, 1000);
+    let inch2m = Rational::new(254, 10000);
+    let feet2m = &Rational::from(12) * &inch2m;
+    let yard2m = &Rational::from(36) * &inch2m;
+    let mile2m = &Rational::from(63360) * &inch2m;
+    let nautical_mile2m = Rational::from(1852);
+    let au2m = Rational::from(149597870700);
+    // The Vienna fathom (bécsi öl) behind the Hungarian land units.
+    let ol2m = Rational::new(1896484, 1000000);
+    let hour2s = Rational::from(3600);
+    let day2s = Rational::from(86400);
+    let pound2kg = Rational::new(45359237, 100000000);
+    // Standard gravity turns a pound of mass into a pound-force.
+    let lbf2n = &pound2kg * &Rational::new(980665, 100000);
+    let gallon2m3 = Rational::new(3785411784, 1000000000000);
+    let imp_gallon2m3 = Rational::new(454609, 100000000);
+    let us_floz2m3 = &gallon2m3 / &Rational::from(128);
     match a {
-        Unit::LenM => wrap(_1),
-        Unit::LenKm => wrap(_1000),
-        Unit::LenCm => wrap(_1_100),
-        Unit::LenMm => wrap(_1_1000),
-        Unit::LenInch => wrap(INCH2M),
-        Unit::LenFeet => wrap(FEET2INCH * INCH2M),
-        Unit::LenYard => wrap(YARD2INCH * INCH2M),
-        Unit::LenMile => wrap(MILE2INCH * INCH2M),
-        Unit::AreaM => wrap(_1),
-        Unit::AreaKm => wrap(_1000.pow(2)),
-        Unit::AreaCm => wrap(_1_100.pow(2)),
-        Unit::AreaMm => wrap(_1_1000.pow(2)),
-        Unit::AreaInch => wrap(INCH2M.pow(2)),
-        Unit::AreaFeet => wrap((FEET2INCH * INCH2M).pow(2)),
-        Unit::AreaYard => wrap((YARD2INCH * INCH2M).pow(2)),
-        Unit::AreaMile => wrap((MILE2INCH * INCH2M).pow(2)),
-        Unit::VolM => wrap(_1000),
-        Unit::VolCm => wrap(_1_100.pow(3) * _1000),
-        Unit::VolMm => wrap(_1_1000.pow(3) * _1000),
-        Unit::VolInch => wrap(INCH2M.pow(3) * _1000),
-        Unit::VolFeet => wrap((FEET2INCH * INCH2M).pow(3) * _1000),
-        Unit::VolYard => wrap((YARD2INCH * INCH2M).pow(3) * _1000),
-        Unit::VolMilliLiter => wrap(_1_1000),
-        Unit::VolLiter => wrap(_1),
-        Unit::VolPint => wrap(Rational::new(3785411784, 8000000000)),
-        Unit::VolGallon => wrap(Rational::new(3785411784, 1000000000)),
-        Unit::VolCup => wrap(Rational::new(3785411784, 16000000000)),
-        Unit::MassG => wrap(_1_1000),
-        Unit::MassKg => wrap(_1),
-        Unit::MassOunce => wrap(Rational::new(45359237, 800000000)),
-        Unit::MassPound => wrap(Rational::new(45359237, 100000000)),
-        Unit::TimeSec => wrap(_1),
+        Unit::LenM => wrap(one),
+        Unit::LenInch => wrap(inch2m),
+        Unit::LenFeet => wrap(feet2m),
+        Unit::LenYard => wrap(yard2m),
+        Unit::LenMile => wrap(mile2m),
+        Unit::LenNauticalMile => wrap(nautical_mile2m),
+        Unit::LenAngstrom => wrap(Rational::new(1, 10000000000)),
+        // A Julian year (365.25 days) of light at 299792458 m/s.
+        Unit::LenLightYear => wrap(Rational::from(9460730472580800)),
+        // 648000/π AU, so like the radian it can only be a float.
+        Unit::LenParsec => Ok(Number::Float(
+            f64::from(&au2m) * 648000.0 / std::f64::consts::PI,
+        )),
+        Unit::LenAu => wrap(au2m),
+        Unit::LenFurlong => wrap(&Rational::from(660) * &feet2m),
+        Unit::LenChain => wrap(&Rational::from(66) * &feet2m),
+        Unit::AreaM => wrap(one),
+        Unit::AreaInch => wrap(inch2m.pow(2)),
+        Unit::AreaFeet => wrap(feet2m.pow(2)),
+        Unit::AreaYard => wrap(yard2m.pow(2)),
+        Unit::AreaMile => wrap(mile2m.pow(2)),
+        Unit::LenPoint => wrap(&inch2m / &Rational::from(72)),
+        Unit::LenPica => wrap(&inch2m / &Rational::from(6)),
+        // Screen units depend on `units.dpi` and `units.em` in the config.
+        Unit::LenPixel | Unit::LenEm => {
+            let units = config::units();
+            if units.dpi == 0 {
+                return Err(CalcError::DivByZero);
+            }
+            let px = &inch2m / &Rational::from(i64::from(units.dpi));
+            match a {
+                Unit::LenEm => wrap(&Rational::from(i64::from(units.em)) * &px),
+                _ => wrap(px),
+            }
+        }
+        Unit::AreaAre => wrap(Rational::from(100)),
+        Unit::AreaHectare => wrap(Rational::from(10000)),
+        Unit::AreaAcre => wrap(&Rational::from(43560) * &feet2m.pow(2)),
+        Unit::AreaSquareRod => wrap(&Rational::new(1089, 4) * &feet2m.pow(2)),
+        Unit::AreaNegyszogol => wrap(ol2m.pow(2)),
+        Unit::AreaHold => wrap(&Rational::from(1600) * &ol2m.pow(2)),
+        Unit::VolM => wrap(one),
+        Unit::VolInch => wrap(inch2m.pow(3)),
+        Unit::VolFeet => wrap(feet2m.pow(3)),
+        Unit::VolYard => wrap(yard2m.pow(3)),
+        Unit::VolLiter => wrap(milli),
+        Unit::VolGallonUs => wrap(gallon2m3),
+        Unit::VolGallonImp => wrap(imp_gallon2m3),
+        Unit::VolPintUs => wrap(&gallon2m3 / &Rational::from(8)),
+        Unit::VolPintImp => wrap(&imp_gallon2m3 / &Rational::from(8)),
+        Unit::VolCupUs => wrap(&gallon2m3 / &Rational::from(16)),
+        Unit::VolCupMetric => wrap(Rational::new(1, 4000)),
+        Unit::VolFlOzUs => wrap(us_floz2m3),
+        Unit::VolFlOzImp => wrap(&imp_gallon2m3 / &Rational::from(160)),
+        Unit::VolTbspUs => wrap(&us_floz2m3 / &Rational::from(2)),
+        Unit::VolTbspMetric => wrap(Rational::new(15, 1000000)),
+        Unit::VolTspUs => wrap(&us_floz2m3 / &Rational::from(6)),
+        Unit::VolTspMetric => wrap(Rational::new(5, 1000000)),
+        Unit::MassG => wrap(milli),
+        Unit::MassOunce => wrap(&pound2kg / &Rational::from(16)),
+        Unit::MassTonne => wrap(Rational::from(1000)),
+        Unit::MassShortTon => wrap(&pound2kg * &Rational::from(2000)),
+        Unit::MassLongTon => wrap(&pound2kg * &Rational::from(2240)),
+        Unit::MassStone => wrap(&pound2kg * &Rational::from(14)),
+        // A troy ounce is 480 grains, and a grain 1/7000 lb.
+        Unit::MassTroyOunce => wrap(&pound2kg * &Rational::new(480, 7000)),
+        Unit::MassCarat => wrap(Rational::new(1, 5000)),
+        Unit::MassGrain => wrap(&pound2kg / &Rational::from(7000)),
+        Unit::MassPound => wrap(pound2kg),
+        Unit::TimeSec => wrap(one),
         Unit::TimeMin => wrap(Rational::new(60, 1)),
-        Unit::TimeHour => wrap(Rational::new(3600, 1)),
-        _ => {
-            return Err(CalcError::ConversionError);
+        Unit::TimeHour => wrap(hour2s),
+        Unit::TimeDay => wrap(day2s),
+        Unit::TimeWeek => wrap(&Rational::from(7) * &day2s),
+        Unit::TimeFortnight => wrap(&Rational::from(14) * &day2s),
+        // Julian years, as in the light-year; a month is a twelfth of one.
+        Unit::TimeMonth => wrap(&(&Rational::new(36525, 100) * &day2s) / &Rational::from(12)),
+        Unit::TimeYear => wrap(&Rational::new(36525, 100) * &day2s),
+        Unit::SpeedMph => wrap(&mile2m / &hour2s),
+        Unit::SpeedKnot => wrap(&nautical_mile2m / &hour2s),
+        Unit::DataBit => wrap(one),
+        Unit::DataByte => wrap(Rational::from(8)),
+        Unit::EnergyJoule => wrap(one),
+        Unit::EnergyWh => wrap(hour2s),
+        Unit::EnergyCal => wrap(Rational::new(4184, 1000)),
+        Unit::EnergyBtu => wrap(Rational::new(105505585262, 100000000)),
+        Unit::PowerWatt => wrap(one),
+        // Mechanical horsepower: 550 ft*lbf/s.
+        Unit::PowerHp => wrap(&(&Rational::from(550) * &feet2m) * &lbf2n),
+        Unit::PressurePa => wrap(one),
+        Unit::PressureBar => wrap(Rational::from(100000)),
+        Unit::PressurePsi => wrap(&lbf2n / &inch2m.pow(2)),
+        Unit::PressureAtm => wrap(Rational::from(101325)),
+        // The conventional millimetre of mercury, not exactly 1/760 atm.
+        Unit::PressureMmHg => wrap(Rational::new(133322387415, 1000000000)),
+        // Fuel units are volume per distance (or the reverse), in m3/m.
+        Unit::FuelL100km => wrap(&milli / &Rational::from(100000)),
+        Unit::FuelMpg => wrap(&mile2m / &gallon2m3),
+        Unit::FuelMpgImp => wrap(&mile2m / &imp_gallon2m3),
+        Unit::FreqHz => wrap(one),
+        Unit::FreqRpm | Unit::FreqBpm => wrap(Rational::new(1, 60)),
+        Unit::ElecVolt
+        | Unit::ElecAmpere
+        | Unit::ElecOhm
+        | Unit::ElecFarad
+        | Unit::ElecHenry
+        | Unit::ElecCoulomb => wrap(one),
+        // Angles are based on the degree so that everything but the radian is exact.
+        Unit::AngleDeg => wrap(one),
+        Unit::AngleRad => Ok(Number::Float(180.0 / std::f64::consts::PI)),
+        Unit::AngleGrad => wrap(Rational::new(9, 10)),
+        Unit::AngleTurn => wrap(Rational::from(360)),
+        Unit::AngleArcmin => wrap(Rational::new(1, 60)),
+        Unit::AngleArcsec => wrap(Rational::new(1, 3600)),
+        // Inside derived units temperatures act as intervals (per kelvin);
+        // absolute readings are converted by `convert_temp` instead.
+        Unit::TempC => wrap(one),
+        Unit::TempF => wrap(Rational::new(5, 9)),
+        Unit::TempK => wrap(one),
+        Unit::TempR => wrap(Rational::new(5, 9)),
+        Unit::TempDeltaC => wrap(one),
+        Unit::TempDeltaF => wrap(Rational::new(5, 9)),
+        Unit::TempDeltaK => wrap(one),
+        Unit::TempDeltaR => wrap(Rational::new(5, 9)),
+        Unit::Prefixed(prefix, base) => {
+            number_op::mul(prefix.factor().into(), get_default_factor(base)?)
         }
+        Unit::Derived(parts) => {
+            let mut factor = Number::from(1);
+            for (unit, exp) in parts {
+                // Currency parts have no fixed factor; `convert` applies the
+                // exchange rate separately (see `currency_part`).
+                if let Unit::Curr(_) = unit {
+                    continue;
+                }
+                let f = number_op::pow(get_default_factor(unit)?, Number::from(*exp as i64))?;
+                factor = number_op::mul(factor, f)?;
+            }
+            Ok(factor)
+        }
+        Unit::Curr(_) => Err(CalcError::ConversionError),
+        Unit::Custom(name) => custom_unit(name)
+            .map(|custom| custom.factor.clone())
+            .ok_or(CalcError::ConversionError),
     }
 }
 
-pub fn get_unit_name(a: &Unit) -> &str {
-    match a {
-        Unit::Curr(x) => x,
+pub fn get_unit_name(a: &Unit) -> String {
+    let name = match a {
+        Unit::Derived(parts) => return format_derived(parts),
+        // A bare `F` or `C` reads back as a temperature, so farad and coulomb
+        // only use their symbol after a prefix (`µF`, `mC`).
+        Unit::Prefixed(prefix, base) => {
+            let base = match **base {
+                Unit::ElecFarad => "F".to_string(),
+                Unit::ElecCoulomb => "C".to_string(),
+                _ => get_unit_name(base),
+            };
+            return format!("{}{}", prefix.symbol(), base);
+        }
+        Unit::Curr(x) | Unit::Custom(x) => x,
         Unit::LenM => "m",
-        Unit::LenKm => "km",
-        Unit::LenCm => "cm",
-        Unit::LenMm => "mm",
         Unit::LenInch => "\"",
         Unit::LenFeet => "'",
         Unit::LenYard => "yd",
         Unit::LenMile => "mi",
+        Unit::LenNauticalMile => "nmi",
+        Unit::LenAngstrom => "Å",
+        Unit::LenAu => "AU",
+        Unit::LenLightYear => "ly",
+        Unit::LenParsec => "pc",
+        Unit::LenFurlong => "furlong",
+        Unit::LenChain => "ch",
         Unit::AreaM => "m2",
-        Unit::AreaKm => "km2",
-        Unit::AreaCm => "cm2",
-        Unit::AreaMm => "mm2",
         Unit::AreaInch => "in2",
         Unit::AreaFeet => "ft2",
         Unit::AreaYard => "yd2",
         Unit::AreaMile => "mi2",
+        Unit::LenPoint => "pts",
+        Unit::LenPica => "pica",
+        Unit::LenPixel => "px",
+        Unit::LenEm => "em",
+        Unit::AreaAre => "are",
+        Unit::AreaHectare => "ha",
+        Unit::AreaAcre => "acre",
+        Unit::AreaSquareRod => "sqrod",
+        Unit::AreaNegyszogol => "négyszögöl",
+        Unit::AreaHold => "hold",
         Unit::VolLiter => "l",
-        Unit::VolMilliLiter => "ml",
         Unit::VolM => "m3",
-        Unit::VolCm => "cm3",
-        Unit::VolMm => "mm3",
         Unit::VolInch => "in3",
         Unit::VolFeet => "ft3",
         Unit::VolYard => "yd3",
-        Unit::VolPint => "pint",
-        Unit::VolGallon => "gallon",
-        Unit::VolCup => "cup",
+        Unit::VolGallonUs => regional_name(Region::Us, "gallon", "usgal"),
+        Unit::VolGallonImp => regional_name(Region::Imperial, "gallon", "impgal"),
+        Unit::VolPintUs => regional_name(Region::Us, "pint", "uspt"),
+        Unit::VolPintImp => regional_name(Region::Imperial, "pint", "imppt"),
+        Unit::VolCupUs => regional_name(Region::Us, "cup", "uscup"),
+        Unit::VolCupMetric => regional_name(Region::Imperial, "cup", "mcup"),
+        Unit::VolFlOzUs => regional_name(Region::Us, "fl oz", "usfloz"),
+        Unit::VolFlOzImp => regional_name(Region::Imperial, "fl oz", "impfloz"),
+        Unit::VolTbspUs => regional_name(Region::Us, "tbsp", "ustbsp"),
+        Unit::VolTbspMetric => regional_name(Region::Imperial, "tbsp", "mtbsp"),
+        Unit::VolTspUs => regional_name(Region::Us, "tsp", "ustsp"),
+        Unit::VolTspMetric => regional_name(Region::Imperial, "tsp", "mtsp"),
         Unit::MassG => "g",
-        Unit::MassKg => "kg",
         Unit::MassOunce => "oz",
         Unit::MassPound => "lb",
+        Unit::MassTonne => "t",
+        Unit::MassShortTon => regional_name(Region::Us, "ton", "shortton"),
+        Unit::MassLongTon => regional_name(Region::Imperial, "ton", "longton"),
+        Unit::MassStone => "st",
+        Unit::MassTroyOunce => "ozt",
+        Unit::MassCarat => "ct",
+        Unit::MassGrain => "gr",
+        Unit::SpeedMph => "mph",
+        Unit::SpeedKnot => "kn",
+        Unit::DataBit => "bit",
+        Unit::DataByte => "B",
+        Unit::EnergyJoule => "J",
+        Unit::EnergyWh => "Wh",
+        Unit::EnergyCal => "cal",
+        Unit::EnergyBtu => "BTU",
+        Unit::PowerWatt => "W",
+        Unit::PowerHp => "hp",
+        Unit::PressurePa => "Pa",
+        Unit::PressureBar => "bar",
+        Unit::PressurePsi => "psi",
+        Unit::PressureAtm => "atm",
+        Unit::PressureMmHg => "mmHg",
+        Unit::FuelL100km => "L/100km",
+        Unit::FuelMpg => "mpg",
+        Unit::FuelMpgImp => "mpg_imp",
+        Unit::FreqHz => "Hz",
+        Unit::FreqRpm => "rpm",
+        Unit::FreqBpm => "bpm",
+        Unit::ElecVolt => "V",
+        Unit::ElecAmpere => "A",
+        Unit::ElecOhm => "Ω",
+        Unit::ElecFarad => "farad",
+        Unit::ElecHenry => "H",
+        Unit::ElecCoulomb => "coulomb",
         Unit::TempC => "C",
         Unit::TempF => "F",
+        Unit::TempK => "K",
+        Unit::TempR => "R",
+        Unit::TempDeltaC => "ΔC",
+        Unit::TempDeltaF => "ΔF",
+        Unit::TempDeltaK => "ΔK",
+        Unit::TempDeltaR => "ΔR",
         Unit::TimeSec => "s",
         Unit::TimeMin => "min",
         Unit::TimeHour => "h",
+        Unit::TimeDay => "d",
+        Unit::TimeWeek => "wk",
+        Unit::TimeFortnight => "fortnight",
+        Unit::TimeMonth => "mo",
+        Unit::TimeYear => "yr",
+        Unit::AngleDeg => "deg",
+        Unit::AngleRad => "rad",
+        Unit::AngleGrad => "grad",
+        Unit::AngleTurn => "turn",
+        Unit::AngleArcmin => "arcmin",
+        Unit::AngleArcsec => "arcsec",
+    };
+    name.to_string()
+}
+
+// A regional unit goes by its bare name in its own region and by its qualified
+// name elsewhere, so `impgal` prints as `gallon` once the region is imperial.
+fn regional_name(region: Region, bare: &'static str, qualified: &'static str) -> &'static str {
+    if config::units().region == region {
+        bare
+    } else {
+        qualified
+    }
+}
+
+/// The variant a bare regional name (`gallon`, `cup`, ...) stands for under
+/// the configured region. The imperial region takes metric cups and spoons,
+/// as used in the UK and Commonwealth.
+pub fn regional(us: Unit, imperial: Unit) -> Unit {
+    match config::units().region {
+        Region::Us => us,
+        Region::Imperial => imperial,
     }
 }
 
+/// Renders derived units as `km/h`, `kg*m/s^2`; a squared or cubed part is
+/// shown by its named unit when there is one (`m2/s` and `km2`, not `m^2/s`).
+fn format_derived(parts: &[(Unit, i32)]) -> String {
+    let named = |unit: &Unit, exp: i32| match unit {
+        Unit::Prefixed(prefix, base) => named_derived(&[((**base).clone(), exp)])
+            .map(|named| format!("{}{}", prefix.symbol(), get_unit_name(&named))),
+        _ => named_derived(&[(unit.clone(), exp)]).map(|named| get_unit_name(&named)),
+    };
+    let name = |unit: &Unit, exp: i32| match (exp, named(unit, exp)) {
+        (1, _) => get_unit_name(unit),
+        (_, Some(named)) => named,
+        _ => format!("{}^{}", get_unit_name(unit), exp),
+    };
+    let num: Vec<String> = parts
+        .iter()
+        .filter(|(_, exp)| *exp > 0)
+        .map(|(unit, exp)| name(unit, *exp))
+        .collect();
+    let den: Vec<String> = parts
+        .iter()
+        .filter(|(_, exp)| *exp < 0)
+        .map(|(unit, exp)| name(unit, -exp))
+        .collect();
+    let mut out = if num.is_empty() {
+        String::from("1")
+    } else {
+        num.join("*")
+    };
+    for part in den {
+        out.push('/');
+        out.push_str(&part);
+    }
+    out
+}
+
 pub fn get_unit_type(a: &Unit) -> UnitType {
     match a {
         Unit::Curr(_) => UnitType::Currency,
+        Unit::Custom(_) => UnitType::Custom,
         Unit::LenM => UnitType::Length,
-        Unit::LenKm => UnitType::Length,
-        Unit::LenCm => UnitType::Length,
-        Unit::LenMm => UnitType::Length,
         Unit::LenInch => UnitType::Length,
         Unit::LenFeet => UnitType::Length,
         Unit::LenYard => UnitType::Length,
         Unit::LenMile => UnitType::Length,
+        Unit::LenNauticalMile => UnitType::Length,
+        Unit::LenAngstrom => UnitType::Length,
+        Unit::LenAu => UnitType::Length,
+        Unit::LenLightYear => UnitType::Length,
+        Unit::LenParsec => UnitType::Length,
+        Unit::LenFurlong => UnitType::Length,
+        Unit::LenChain => UnitType::Length,
         Unit::AreaM => UnitType::Area,
-        Unit::AreaKm => UnitType::Area,
-        Unit::AreaCm => UnitType::Area,
-        Unit::AreaMm => UnitType::Area,
         Unit::AreaInch => UnitType::Area,
         Unit::AreaFeet => UnitType::Area,
         Unit::AreaYard => UnitType::Area,
         Unit::AreaMile => UnitType::Area,
+        Unit::LenPoint => UnitType::Length,
+        Unit::LenPica => UnitType::Length,
+        Unit::LenPixel => UnitType::Length,
+        Unit::LenEm => UnitType::Length,
+        Unit::AreaAre => UnitType::Area,
+        Unit::AreaHectare => UnitType::Area,
+        Unit::AreaAcre => UnitType::Area,
+        Unit::AreaSquareRod => UnitType::Area,
+        Unit::AreaNegyszogol => UnitType::Area,
+        Unit::AreaHold => UnitType::Area,
         Unit::VolLiter => UnitType::Volume,
-        Unit::VolMilliLiter => UnitType::Volume,
         Unit::VolM => UnitType::Volume,
-        Unit::VolCm => UnitType::Volume,
-        Unit::VolMm => UnitType::Volume,
         Unit::VolInch => UnitType::Volume,
         Unit::VolFeet => UnitType::Volume,
         Unit::VolYard => UnitType::Volume,
-        Unit::VolPint => UnitType::Volume,
-        Unit::VolGallon => UnitType::Volume,
-        Unit::VolCup => UnitType::Volume,
+        Unit::VolGallonUs => UnitType::Volume,
+        Unit::VolGallonImp => UnitType::Volume,
+        Unit::VolPintUs => UnitType::Volume,
+        Unit::VolPintImp => UnitType::Volume,
+        Unit::VolCupUs => UnitType::Volume,
+        Unit::VolCupMetric => UnitType::Volume,
+        Unit::VolFlOzUs => UnitType::Volume,
+        Unit::VolFlOzImp => UnitType::Volume,
+        Unit::VolTbspUs => UnitType::Volume,
+        Unit::VolTbspMetric => UnitType::Volume,
+        Unit::VolTspUs => UnitType::Volume,
+        Unit::VolTspMetric => UnitType::Volume,
         Unit::MassG => UnitType::Mass,
-        Unit::MassKg => UnitType::Mass,
         Unit::MassOunce => UnitType::Mass,
         Unit::MassPound => UnitType::Mass,
+        Unit::MassTonne => UnitType::Mass,
+        Unit::MassShortTon => UnitType::Mass,
+        Unit::MassLongTon => UnitType::Mass,
+        Unit::MassStone => UnitType::Mass,
+        Unit::MassTroyOunce => UnitType::Mass,
+        Unit::MassCarat => UnitType::Mass,
+        Unit::MassGrain => UnitType::Mass,
+        Unit::SpeedMph => UnitType::Speed,
+        Unit::SpeedKnot => UnitType::Speed,
+        Unit::DataBit => UnitType::Data,
+        Unit::DataByte => UnitType::Data,
+        Unit::EnergyJoule => UnitType::Energy,
+        Unit::EnergyWh => UnitType::Energy,
+        Unit::EnergyCal => UnitType::Energy,
+        Unit::EnergyBtu => UnitType::Energy,
+        Unit::PowerWatt => UnitType::Power,
+        Unit::PowerHp => UnitType::Power,
+        Unit::PressurePa => UnitType::Pressure,
+        Unit::PressureBar => UnitType::Pressure,
+        Unit::PressurePsi => UnitType::Pressure,
+        Unit::PressureAtm => UnitType::Pressure,
+        Unit::PressureMmHg => UnitType::Pressure,
+        Unit::FuelL100km => UnitType::FuelConsumption,
+        Unit::FuelMpg => UnitType::FuelEconomy,
+        Unit::FuelMpgImp => UnitType::FuelEconomy,
+        Unit::FreqHz => UnitType::Frequency,
+        Unit::FreqRpm => UnitType::Frequency,
+        Unit::FreqBpm => UnitType::Frequency,
+        Unit::ElecVolt => UnitType::Voltage,
+        Unit::ElecAmpere => UnitType::Current,
+        Unit::ElecOhm => UnitType::Resistance,
+        Unit::ElecFarad => UnitType::Capacitance,
+        Unit::ElecHenry => UnitType::Inductance,
+        Unit::ElecCoulomb => UnitType::Charge,
         Unit::TempC => UnitType::Temperature,
         Unit::TempF => UnitType::Temperature,
+        Unit::TempK => UnitType::Temperature,
+        Unit::TempR => UnitType::Temperature,
+        Unit::TempDeltaC => UnitType::TemperatureInterval,
+        Unit::TempDeltaF => UnitType::TemperatureInterval,
+        Unit::TempDeltaK => UnitType::TemperatureInterval,
+        Unit::TempDeltaR => UnitType::TemperatureInterval,
         Unit::TimeSec => UnitType::Time,
         Unit::TimeMin => UnitType::Time,
         Unit::TimeHour => UnitType::Time,
+        Unit::TimeDay => UnitType::Time,
+        Unit::TimeWeek => UnitType::Time,
+        Unit::TimeFortnight => UnitType::Time,
+        Unit::TimeMonth => UnitType::Time,
+        Unit::TimeYear => UnitType::Time,
+        Unit::AngleDeg => UnitType::Angle,
+        Unit::AngleRad => UnitType::Angle,
+        Unit::AngleGrad => UnitType::Angle,
+        Unit::AngleTurn => UnitType::Angle,
+        Unit::AngleArcmin => UnitType::Angle,
+        Unit::AngleArcsec => UnitType::Angle,
+        Unit::Prefixed(_, base) => get_unit_type(base),
+        Unit::Derived(_) => UnitType::Derived,
+    }
+}
+
+fn type_dim(unit_type: UnitType) -> Dim {
+    match unit_type {
+        UnitType::Length => Dim::of(BaseDim::Length, 1),
+        UnitType::Area => Dim::of(BaseDim::Length, 2),
+        UnitType::Volume => Dim::of(BaseDim::Length, 3),
+        UnitType::Mass => Dim::of(BaseDim::Mass, 1),
+        UnitType::Temperature | UnitType::TemperatureInterval => Dim::of(BaseDim::Temperature, 1),
+        UnitType::Time => Dim::of(BaseDim::Time, 1),
+        UnitType::Speed => Dim::of(BaseDim::Length, 1).mul(&Dim::of(BaseDim::Time, 1), -1),
+        // kg*m2/s2 and kg*m2/s3
+        UnitType::Energy => Dim::of(BaseDim::Mass, 1)
+            .mul(&Dim::of(BaseDim::Length, 1), 2)
+            .mul(&Dim::of(BaseDim::Time, 1), -2),
+        UnitType::Power => type_dim(UnitType::Energy).mul(&Dim::of(BaseDim::Time, 1), -1),
+        // kg/(m*s2)
+        UnitType::Pressure => type_dim(UnitType::Energy).mul(&Dim::of(BaseDim::Length, 1), -3),
+        // Volume per distance, and distance per volume.
+        UnitType::FuelConsumption => Dim::of(BaseDim::Length, 2),
+        UnitType::FuelEconomy => Dim::of(BaseDim::Length, -2),
+        UnitType::Frequency => Dim::of(BaseDim::Time, -1),
+        // W/A, V/A, A*s, C/V and Ω*s
+        UnitType::Current => Dim::of(BaseDim::Current, 1),
+        UnitType::Voltage => type_dim(UnitType::Power).mul(&Dim::of(BaseDim::Current, 1), -1),
+        UnitType::Resistance => type_dim(UnitType::Voltage).mul(&Dim::of(BaseDim::Current, 1), -1),
+        UnitType::Charge => Dim::of(BaseDim::Current, 1).mul(&Dim::of(BaseDim::Time, 1), 1),
+        UnitType::Capacitance => type_dim(UnitType::Charge).mul(&type_dim(UnitType::Voltage), -1),
+        UnitType::Inductance => type_dim(UnitType::Resistance).mul(&Dim::of(BaseDim::Time, 1), 1),
+        UnitType::Data => Dim::of(BaseDim::Data, 1),
+        UnitType::Currency => Dim::of(BaseDim::Currency, 1),
+        UnitType::Angle => Dim::of(BaseDim::Angle, 1),
+        // Derived and custom units carry their own dimension (see `get_dim`).
+        UnitType::Custom | UnitType::Derived => Dim::default(),
+    }
+}
+
+pub fn get_dim(a: &Unit) -> Dim {
+    match a {
+        Unit::Derived(parts) => parts.iter().fold(Dim::default(), |dim, (unit, exp)| {
+            dim.mul(&get_dim(unit), *exp)
+        }),
+        Unit::Custom(name) => {
+            custom_unit(name).map_or_else(Dim::default, |custom| custom.dim.clone())
+        }
+        _ => type_dim(get_unit_type(a)),
     }
 }
 
+/// Named units that stand for a product of other units, e.g. `m2` = `m^2`.
+/// Arithmetic expands them into their parts and `named_derived` folds a result
+/// back into the named form.
+static NAMED_DERIVED: &[Unit] = &[
+    Unit::AreaM,
+    Unit::AreaInch,
+    Unit::AreaFeet,
+    Unit::AreaYard,
+    Unit::AreaMile,
+    Unit::VolM,
+    Unit::VolInch,
+    Unit::VolFeet,
+    Unit::VolYard,
+    Unit::SpeedMph,
+    Unit::SpeedKnot,
+    Unit::FuelMpg,
+    Unit::FuelMpgImp,
+];
+
+fn derived_parts(a: &Unit) -> Option<Vec<(Unit, i32)>> {
+    let parts = match a {
+        Unit::AreaM => vec![(Unit::LenM, 2)],
+        Unit::AreaInch => vec![(Unit::LenInch, 2)],
+        Unit::AreaFeet => vec![(Unit::LenFeet, 2)],
+        Unit::AreaYard => vec![(Unit::LenYard, 2)],
+        Unit::AreaMile => vec![(Unit::LenMile, 2)],
+        Unit::VolM => vec![(Unit::LenM, 3)],
+        Unit::VolInch => vec![(Unit::LenInch, 3)],
+        Unit::VolFeet => vec![(Unit::LenFeet, 3)],
+        Unit::VolYard => vec![(Unit::LenYard, 3)],
+        Unit::SpeedMph => vec![(Unit::LenMile, 1), (Unit::TimeHour, -1)],
+        Unit::SpeedKnot => vec![(Unit::LenNauticalMile, 1), (Unit::TimeHour, -1)],
+        Unit::FuelMpg => vec![(Unit::LenMile, 1), (Unit::VolGallonUs, -1)],
+        Unit::FuelMpgImp => vec![(Unit::LenMile, 1), (Unit::VolGallonImp, -1)],
+        _ => return None,
+    };
+    Some(parts)
+}
+
+/// `prefix` applied to `base`. Named derived units take the prefix on their
+/// length, as in `km2` = `km^2` (not a thousand square metres).
+pub fn prefixed(prefix: Prefix, base: Unit) -> Unit {
+    match derived_parts(&base).as_deref() {
+        Some([(part, exp)]) => {
+            Unit::Derived(vec![(Unit::Prefixed(prefix, Box::new(part.clone())), *exp)])
+        }
+        _ => Unit::Prefixed(prefix, Box::new(base)),
+    }
+}
+
+fn named_derived(parts: &[(Unit, i32)]) -> Option<Unit> {
+    NAMED_DERIVED.iter().find_map(|named| {
+        let named_parts = derived_parts(named)?;
+        let same =
+            named_parts.len() == parts.len() && named_parts.iter().all(|part| parts.contains(part));
+        same.then(|| named.clone())
+    })
+}
+
+fn expand(a: Unit) -> Vec<(Unit, i32)> {
+    match a {
+        Unit::Derived(parts) => parts,
+        a => derived_parts(&a).unwrap_or_else(|| vec![(a, 1)]),
+    }
+}
+
+fn from_parts(mut parts: Vec<(Unit, i32)>) -> Option<Unit> {
+    parts.retain(|(_, exp)| *exp != 0);
+    match parts.as_slice() {
+        [] => None,
+        [(_, 1)] => parts.pop().map(|(unit, _)| unit),
+        _ => Some(named_derived(&parts).unwrap_or(Unit::Derived(parts))),
+    }
+}
+
+// Parts of the same type (e.g. km and m) are merged into one so that results
+// come out as `km2` rather than `km*m`. Currencies only merge with themselves:
+// there is no fixed factor between two codes. Custom units don't share a type
+// with each other, so they only merge with themselves too.
+fn mergeable(a: &Unit, b: &Unit) -> bool {
+    a == b
+        || (get_unit_type(a) == get_unit_type(b)
+            && !matches!(get_unit_type(a), UnitType::Currency | UnitType::Custom))
+}
+
+// Absolute temperatures are affine: convert through kelvin.
 fn convert_temp(val: Number, from: &Unit, to: &Unit) -> Result<Number, CalcError> {
-    const FACTOR: Number = Number::Rational(Rational::new(18, 10));
-    const BIAS: Number = Number::Int(32);
+    let kelvin = number_op::add(
+        number_op::mul(val, get_default_factor(from)?)?,
+        temp_zero(from),
+    )?;
+    number_op::div(
+        number_op::sub(kelvin, temp_zero(to))?,
+        get_default_factor(to)?,
+    )
+}
+
+// Fuel consumption (volume per distance) and economy (distance per volume) are
+// reciprocal: 5 L/100km is 20 km/l. So are a frequency and its period: 50 Hz
+// is 20 ms.
+fn convert_reciprocal(val: Number, from: &Unit, to: &Unit) -> Result<Number, CalcError> {
+    let product = number_op::mul(
+        number_op::mul(val, get_default_factor(from)?)?,
+        get_default_factor(to)?,
+    )?;
+    number_op::div(Number::from(1), product)
+}
+
+fn is_reciprocal(a: &Unit) -> bool {
+    matches!(
+        get_unit_type(a),
+        UnitType::FuelConsumption | UnitType::FuelEconomy | UnitType::Frequency
+    )
+}
+
+/// Zero of an absolute temperature scale, in kelvin.
+fn temp_zero(a: &Unit) -> Number {
+    match a {
+        Unit::TempC => Rational::new(27315, 100).into(),
+        Unit::TempF => Rational::new(45967, 180).into(),
+        _ => Number::from(0),
+    }
+}
 
-    match (from, to) {
-        (Unit::TempC, Unit::TempF) => return Ok(number_op::add(number_op::mul(val, FACTOR), BIAS)),
-        (Unit::TempF, Unit::TempC) => {
-            return Ok(number_op::div(number_op::sub(val, BIAS), FACTOR)?);
+/// The interval unit matching an absolute temperature's scale, e.g. `ΔC` for `C`.
+pub fn temp_interval(a: &Unit) -> Option<Unit> {
+    match a {
+        Unit::TempC => Some(Unit::TempDeltaC),
+        Unit::TempF => Some(Unit::TempDeltaF),
+        Unit::TempK => Some(Unit::TempDeltaK),
+        Unit::TempR => Some(Unit::TempDeltaR),
+        Unit::Prefixed(prefix, base) => {
+            temp_interval(base).map(|base| Unit::Prefixed(*prefix, Box::new(base)))
         }
-        (_, _) => Err(CalcError::ConversionError),
+        _ => None,
     }
 }
 
@@ -224,81 +957,430 @@ fn convert_temp(val: Number, from: &Unit, to: &Unit) -> Result<Number, CalcError
 pub enum CompoundGroup {
     MetricLength,
     ImperialLength,
+    ImperialMass,
     Time,
+    Angle,
 }
 
 pub fn compound_group(u: &Unit) -> Option<CompoundGroup> {
     match u {
-        Unit::LenM | Unit::LenCm => Some(CompoundGroup::MetricLength),
+        Unit::Prefixed(_, base) => compound_group(base),
+        Unit::LenM => Some(CompoundGroup::MetricLength),
         Unit::LenFeet | Unit::LenInch => Some(CompoundGroup::ImperialLength),
-        Unit::TimeHour | Unit::TimeMin | Unit::TimeSec => Some(CompoundGroup::Time),
+        Unit::MassStone | Unit::MassPound | Unit::MassOunce => Some(CompoundGroup::ImperialMass),
+        Unit::TimeDay | Unit::TimeHour | Unit::TimeMin | Unit::TimeSec => Some(CompoundGroup::Time),
+        Unit::AngleDeg | Unit::AngleArcmin | Unit::AngleArcsec => Some(CompoundGroup::Angle),
         _ => None,
     }
 }
 
+/// A unit declared under `[units.custom]`, resolved to a factor and dimension.
+struct CustomUnit {
+    name: String,
+    factor: Number,
+    dim: Dim,
+}
+
+/// What a `[units.custom]` entry stands for.
+pub enum CustomDef {
+    /// A base quantity of its own, e.g. story points.
+    Base,
+    /// A multiple of built-in units, e.g. `1.2 m2`; without a unit it is a
+    /// plain count like a dozen.
+    Quantity(Number, Option<Unit>),
+}
+
+static CUSTOM_UNITS: OnceLock<Vec<CustomUnit>> = OnceLock::new();
+
+/// Registers the custom units once at startup (see `repl::init_custom_units`).
+/// A definition must be a fixed multiple of other units, so absolute
+/// temperatures and currencies are rejected.
+pub fn register_custom(defs: Vec<(String, CustomDef)>) -> Result<(), CalcError> {
+    let mut units = Vec::with_capacity(defs.len());
+    for (idx, (name, def)) in defs.into_iter().enumerate() {
+        let (factor, dim) = match def {
+            CustomDef::Base => (Number::from(1), Dim::of(BaseDim::Custom(idx), 1)),
+            CustomDef::Quantity(num, None) => (num, Dim::default()),
+            CustomDef::Quantity(num, Some(unit)) => {
+                let dim = get_dim(&unit);
+                let priced = dim.0.iter().any(|(base, _)| *base == BaseDim::Currency);
+                if priced || get_unit_type(&unit) == UnitType::Temperature {
+                    return Err(CalcError::ConfigError(format!(
+                        "custom unit {name:?}: {} has no fixed factor",
+                        get_unit_name(&unit)
+                    )));
+                }
+                (number_op::mul(num, get_default_factor(&unit)?)?, dim)
+            }
+        };
+        units.push(CustomUnit { name, factor, dim });
+    }
+    // Registering twice keeps the first set.
+    let _ = CUSTOM_UNITS.set(units);
+    Ok(())
+}
+
+fn custom_unit(name: &str) -> Option<&'static CustomUnit> {
+    CUSTOM_UNITS
+        .get()?
+        .iter()
+        .find(|custom| custom.name == name)
+}
+
+/// The custom unit called `name`, if one was registered.
+pub fn custom(name: &str) -> Option<Unit> {
+    custom_unit(name).map(|custom| Unit::Custom(custom.name.as_str()))
+}
+
 pub fn common_type(a: &Unit, b: &Unit) -> Option<UnitType> {
     let unit_type = get_unit_type(a);
     if unit_type == get_unit_type(b) {
-        return Some(unit_type);
+        Some(unit_type)
     } else {
         None
     }
 }
 
+// The currency a derived unit is priced in, e.g. `HUF` in `HUF/kg`.
+fn currency_part(a: &Unit) -> Option<(&'static str, i32)> {
+    let Unit::Derived(parts) = a else {
+        return None;
+    };
+    parts.iter().find_map(|(unit, exp)| match unit {
+        Unit::Curr(code) => Some((*code, *exp)),
+        _ => None,
+    })
+}
+
 pub fn convert(val: Number, from: &Unit, to: &Unit) -> Result<Number, CalcError> {
-    let Some(unit_type) = common_type(from, to) else {
+    if (is_reciprocal(from) || is_reciprocal(to)) && get_dim(from) == get_dim(to).inverse() {
+        return convert_reciprocal(val, from, to);
+    }
+    if get_dim(from) != get_dim(to) {
         return Err(CalcError::DifferentUnitTypes);
-    };
-    match unit_type {
-        UnitType::Temperature => convert_temp(val, from, to),
-        UnitType::Currency => {
+    }
+    // An absolute temperature is a point, not an amount: it only converts to
+    // other absolute temperatures.
+    let absolute = |u: &Unit| get_unit_type(u) == UnitType::Temperature;
+    if absolute(from) != absolute(to) {
+        return Err(CalcError::DifferentUnitTypes);
+    }
+    match common_type(from, to) {
+        Some(UnitType::Temperature) => convert_temp(val, from, to),
+        Some(UnitType::Currency) => {
             let Unit::Curr(from) = from else {
                 return Err(CalcError::ConversionError);
             };
             let Unit::Curr(to) = to else {
                 return Err(CalcError::ConversionError);
             };
-            return Ok(number_op::mul(
-                val,
-                Number::Rational(currency::convert(*from, *to)?),
-            ));
+            number_op::mul(val, Number::Rational(currency::convert(from, to)?))
         }
         _ => {
-            let rate = number_op::div(get_default_factor(from)?, get_default_factor(to)?)?;
-            Ok(number_op::mul(rate, val))
+            let mut rate = number_op::div(get_default_factor(from)?, get_default_factor(to)?)?;
+            if let (Some((from, exp)), Some((to, _))) = (currency_part(from), currency_part(to)) {
+                if from != to {
+                    let fx = Number::Rational(currency::convert(from, to)?);
+                    rate = number_op::mul(rate, number_op::pow(fx, Number::from(exp as i64))?)?;
+                }
+            }
+            number_op::mul(rate, val)
         }
     }
 }
 
-pub fn single(a: Option<Unit>, b: Option<Unit>) -> Result<Option<Unit>, CalcError> {
-    let Some(a) = a else {
-        return Ok(b);
-    };
-    let Some(_) = b else {
-        return Ok(Some(a));
-    };
-    Err(CalcError::OperateWithUnits)
+/// Unit of `a * b^exp` (`exp` is 1 for a product, -1 for a quotient), plus the
+/// factor the number has to be multiplied by when parts of the same type were
+/// merged (`2 km * 500 m` = `1 km2`, via the factor 1/1000). A dimensionless
+/// result drops its unit and folds the remaining conversion into the factor.
+pub fn combine(
+    a: Option<Unit>,
+    b: Option<Unit>,
+    exp: i32,
+) -> Result<(Number, Option<Unit>), CalcError> {
+    let mut factor = Number::from(1);
+    let mut parts = a.map(expand).unwrap_or_default();
+    for (unit, n) in b.map(expand).unwrap_or_default() {
+        let n = n * exp;
+        match parts.iter_mut().find(|(u, _)| mergeable(u, &unit)) {
+            Some((existing, m)) => {
+                if *existing != unit {
+                    let rate =
+                        number_op::div(get_default_factor(&unit)?, get_default_factor(existing)?)?;
+                    factor = number_op::mul(factor, number_op::pow(rate, Number::from(n as i64))?)?;
+                }
+                *m += n;
+            }
+            None => parts.push((unit, n)),
+        }
+    }
+    match from_parts(parts) {
+        Some(unit) if get_dim(&unit).is_dimensionless() => {
+            Ok((number_op::mul(factor, get_default_factor(&unit)?)?, None))
+        }
+        unit => Ok((factor, unit)),
+    }
+}
+
+/// Unit of `a^exp`, e.g. `m` squared is `m2`.
+pub fn pow(a: Unit, exp: i32) -> Option<Unit> {
+    let parts = expand(a)
+        .into_iter()
+        .map(|(unit, n)| (unit, n * exp))
+        .collect();
+    from_parts(parts)
+}
+
+/// `a` spelled in SI base units (`acre` → `m2`, `kWh` → `kg*m2/s2`), or `None`
+/// when one of its bases has no SI unit here (temperature, currency, ...).
+pub fn si_unit(a: &Unit) -> Option<Unit> {
+    let parts = get_dim(a)
+        .0
+        .into_iter()
+        .map(|(base, exp)| {
+            let unit = match base {
+                BaseDim::Length => Unit::LenM,
+                BaseDim::Mass => Unit::Prefixed(Prefix::Kilo, Box::new(Unit::MassG)),
+                BaseDim::Time => Unit::TimeSec,
+                BaseDim::Current => Unit::ElecAmpere,
+                _ => return None,
+            };
+            Some((unit, exp))
+        })
+        .collect::<Option<Vec<_>>>()?;
+    from_parts(parts)
+}
+
+/// Unit of the `n`-th root of `a`, e.g. the square root of `m2` is `m`, or
+/// `None` if `a` is not a perfect `n`-th power.
+pub fn root(a: Unit, n: i32) -> Option<Unit> {
+    let parts = expand(a);
+    if parts.iter().any(|(_, exp)| exp % n != 0) {
+        return None;
+    }
+    from_parts(
+        parts
+            .into_iter()
+            .map(|(unit, exp)| (unit, exp / n))
+            .collect(),
+    )
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_root() {
+        assert!(root(Unit::AreaM, 2) == Some(Unit::LenM));
+        let cm = Unit::Prefixed(Prefix::Centi, Box::new(Unit::LenM));
+        assert!(root(prefixed(Prefix::Centi, Unit::VolM), 3) == Some(cm));
+        assert!(root(Unit::LenM, 2).is_none());
+    }
+
+    #[test]
+    fn test_convert_grad_to_degree_is_exact() {
+        let result = convert(Number::from(100), &Unit::AngleGrad, &Unit::AngleDeg);
+        assert_eq!(result.unwrap(), Number::from(90));
+    }
+
+    #[test]
+    fn test_convert_fahrenheit_to_kelvin() {
+        let result = convert(Number::from(212), &Unit::TempF, &Unit::TempK);
+        assert_eq!(result.unwrap(), Number::Rational(Rational::new(37315, 100)));
+    }
+
+    #[test]
+    fn test_convert_prefixed_is_exact() {
+        let ug = prefixed(Prefix::Micro, Unit::MassG);
+        let mg = prefixed(Prefix::Mega, Unit::MassG);
+        let result = convert(Number::from(3), &mg, &ug);
+        assert_eq!(result.unwrap(), Number::from(3_000_000_000_000i64));
+        assert_eq!(get_unit_name(&prefixed(Prefix::Centi, Unit::VolM)), "cm3");
+    }
+
+    #[test]
+    fn test_convert_binary_prefix_is_exact() {
+        let gib = prefixed(Prefix::Gibi, Unit::DataByte);
+        let mbit = prefixed(Prefix::Mega, Unit::DataBit);
+        let result = convert(Number::from(1), &gib, &mbit);
+        assert_eq!(
+            result.unwrap(),
+            Number::Rational(Rational::new(1073741824, 125000))
+        );
+    }
+
+    #[test]
+    fn test_convert_horsepower_is_exact() {
+        let result = convert(Number::from(1), &Unit::PowerHp, &Unit::PowerWatt);
+        assert_eq!(
+            result.unwrap(),
+            Number::Rational("745.69987158227022".parse().unwrap())
+        );
+    }
+
+    #[test]
+    fn test_convert_psi_is_exact() {
+        let result = convert(Number::from(1), &Unit::PressurePsi, &Unit::PressurePa);
+        assert_eq!(
+            result.unwrap(),
+            Number::Rational(Rational::new(44482216152605, 6451600000))
+        );
+    }
+
+    #[test]
+    fn test_convert_fuel_is_reciprocal() {
+        let result = convert(Number::from(5), &Unit::FuelL100km, &Unit::FuelMpgImp);
+        assert_eq!(
+            result.unwrap(),
+            Number::Rational(Rational::new(454609000, 8046720))
+        );
+        let kml = Unit::Derived(vec![
+            (prefixed(Prefix::Kilo, Unit::LenM), 1),
+            (Unit::VolLiter, -1),
+        ]);
+        let result = convert(Number::from(5), &Unit::FuelL100km, &kml);
+        assert_eq!(result.unwrap(), Number::from(20));
+    }
+
+    #[test]
+    fn test_convert_kitchen_volumes() {
+        let result = convert(Number::from(3), &Unit::VolTspUs, &Unit::VolTbspUs);
+        assert_eq!(result.unwrap(), Number::from(1));
+        let result = convert(Number::from(1), &Unit::VolPintImp, &Unit::VolFlOzImp);
+        assert_eq!(result.unwrap(), Number::from(20));
+    }
+
+    #[test]
+    fn test_convert_stone_to_kg_is_exact() {
+        let result = convert(Number::from(11), &Unit::MassStone, &Unit::MassPound);
+        assert_eq!(result.unwrap(), Number::from(154));
+        let result = convert(Number::from(1), &Unit::MassTroyOunce, &Unit::MassG);
+        assert_eq!(
+            result.unwrap(),
+            Number::Rational(Rational::new(311034768, 10000000))
+        );
+    }
+
+    #[test]
+    fn test_convert_surveying_lengths_are_exact() {
+        let result = convert(Number::from(1), &Unit::LenFurlong, &Unit::LenChain);
+        assert_eq!(result.unwrap(), Number::from(10));
+        let result = convert(Number::from(8), &Unit::LenFurlong, &Unit::LenMile);
+        assert_eq!(result.unwrap(), Number::from(1));
+    }
+
+    #[test]
+    fn test_convert_calendar_units() {
+        let result = convert(Number::from(3), &Unit::TimeWeek, &Unit::TimeHour);
+        assert_eq!(result.unwrap(), Number::from(504));
+        let result = convert(Number::from(1), &Unit::TimeYear, &Unit::TimeDay);
+        assert_eq!(result.unwrap(), Number::Rational(Rational::new(36525, 100)));
+        let result = convert(Number::from(12), &Unit::TimeMonth, &Unit::TimeYear);
+        assert_eq!(result.unwrap(), Number::from(1));
+    }
+
+    #[test]
+    fn test_convert_land_areas_are_exact() {
+        let result = convert(Number::from(1200), &Unit::AreaNegyszogol, &Unit::AreaM);
+        assert_eq!(
+            result.unwrap(),
+            Number::Rational(Rational::new(674372167923, 156250000))
+        );
+    }
+
+    #[test]
+    fn test_convert_square_rods_to_acre() {
+        let result = convert(Number::from(160), &Unit::AreaSquareRod, &Unit::AreaAcre);
+        assert_eq!(result.unwrap(), Number::from(1));
+    }
+
+    #[test]
+    fn test_convert_frequency_to_period() {
+        let ms = prefixed(Prefix::Milli, Unit::TimeSec);
+        let result = convert(Number::from(50), &Unit::FreqHz, &ms);
+        assert_eq!(result.unwrap(), Number::from(20));
+        let result = convert(Number::from(2), &Unit::TimeSec, &Unit::FreqBpm);
+        assert_eq!(result.unwrap(), Number::from(30));
+    }
+
+    #[test]
+    fn test_electrical_dimensions() {
+        let va = Unit::Derived(vec![(Unit::ElecVolt, 1), (Unit::ElecAmpere, 1)]);
+        assert!(get_dim(&va) == get_dim(&Unit::PowerWatt));
+        let rc = Unit::Derived(vec![(Unit::ElecOhm, 1), (Unit::ElecFarad, 1)]);
+        assert!(get_dim(&rc) == get_dim(&Unit::TimeSec));
+    }
+
+    #[test]
+    fn test_convert_screen_units_use_default_dpi() {
+        let result = convert(Number::from(96), &Unit::LenPixel, &Unit::LenPoint);
+        assert_eq!(result.unwrap(), Number::from(72));
+        let result = convert(Number::from(2), &Unit::LenEm, &Unit::LenPixel);
+        assert_eq!(result.unwrap(), Number::from(32));
+    }
+
     #[test]
     fn test_convert_pint_to_gallon() {
-        let result = convert(Number::Int(1), &Unit::VolPint, &Unit::VolGallon);
+        let result = convert(Number::from(1), &Unit::VolPintUs, &Unit::VolGallonUs);
         assert_eq!(result.unwrap(), Number::Rational(Rational::new(1, 8)));
     }
 
     #[test]
     fn test_convert_fahrenheit_to_celsius() {
-        let result = convert(Number::Int(95), &Unit::TempF, &Unit::TempC);
-        assert_eq!(result.unwrap(), Number::Int(35));
+        let result = convert(Number::from(95), &Unit::TempF, &Unit::TempC);
+        assert_eq!(result.unwrap(), Number::from(35));
+    }
+
+    #[test]
+    fn test_combine_merges_same_type() {
+        let km = prefixed(Prefix::Kilo, Unit::LenM);
+        let (factor, unit) = combine(Some(km), Some(Unit::LenM), 1).unwrap();
+        assert_eq!(factor, Number::Rational(Rational::new(1, 1000)));
+        assert!(unit == Some(prefixed(Prefix::Kilo, Unit::AreaM)));
+    }
+
+    #[test]
+    fn test_combine_cancels_to_unitless() {
+        let (factor, unit) = combine(Some(Unit::LenFeet), Some(Unit::LenInch), -1).unwrap();
+        assert_eq!(factor, Number::from(12));
+        assert!(unit.is_none());
+    }
+
+    #[test]
+    fn test_convert_derived_to_named() {
+        let m2 = pow(Unit::LenM, 2).unwrap();
+        assert!(m2 == Unit::AreaM);
+        let result = convert(Number::from(1), &Unit::AreaFeet, &m2);
+        assert_eq!(
+            result.unwrap(),
+            Number::Rational(Rational::new(145161, 1562500))
+        );
+    }
+
+    #[test]
+    fn test_convert_speed() {
+        let km = prefixed(Prefix::Kilo, Unit::LenM);
+        let kmh = Unit::Derived(vec![(km, 1), (Unit::TimeHour, -1)]);
+        let ms = Unit::Derived(vec![(Unit::LenM, 1), (Unit::TimeSec, -1)]);
+        let result = convert(Number::from(36), &kmh, &ms);
+        assert_eq!(result.unwrap(), Number::from(10));
+    }
+
+    #[test]
+    fn test_convert_mph_to_kmh_is_exact() {
+        let km = prefixed(Prefix::Kilo, Unit::LenM);
+        let kmh = Unit::Derived(vec![(km, 1), (Unit::TimeHour, -1)]);
+        let result = convert(Number::from(60), &Unit::SpeedMph, &kmh);
+        assert_eq!(
+            result.unwrap(),
+            Number::Rational(Rational::new(603504, 6250))
+        );
     }
 
     #[test]
     fn test_convert_celsius_to_fahrenheit() {
-        let result = convert(Number::Int(15), &Unit::TempC, &Unit::TempF);
-        assert_eq!(result.unwrap(), Number::Int(59));
+        let result = convert(Number::from(15), &Unit::TempC, &Unit::TempF);
+        assert_eq!(result.unwrap(), Number::from(59));
     }
 }
@@ -1,8 +1,8 @@
 use std::fmt::{Display, Formatter};
 
-use crate::config::FormatOptions;
-use crate::number::{format_number, Number};
-use crate::unit::{get_unit_name, Unit};
+use crate::config::{FormatOptions, IntConfig, NumberRepr};
+use crate::number::{format_dms, format_number, Number};
+use crate::unit::{self, get_unit_name, Unit, UnitType};
 
 #[derive(Clone)]
 pub struct Value {
@@ -11,8 +11,29 @@ pub struct Value {
 }
 
 pub fn format_value(val: &Value, opts: &FormatOptions) -> String {
+    if opts.repr == NumberRepr::Dms {
+        if let Some(unit) = val
+            .unit
+            .as_ref()
+            .filter(|u| unit::get_unit_type(u) == UnitType::Angle)
+        {
+            if let Ok(degrees) = unit::convert(val.num.clone(), unit, &Unit::AngleDeg) {
+                return format_dms(&degrees, opts);
+            }
+        }
+    }
     if let Some(unit) = &val.unit {
-        format!("{} {}", format_number(&val.num, opts), get_unit_name(unit))
+        // Bare integers stay exact by default (`30!`), but a quantity is a
+        // measurement: a large whole one (`1 ly to m`) upgrades to sci at
+        // `int.sci_upgrade_upper` whenever floats upgrade.
+        let opts = FormatOptions {
+            int: IntConfig {
+                sci_upgrade: opts.int.sci_upgrade || opts.float.sci_upgrade,
+                ..opts.int.clone()
+            },
+            ..opts.clone()
+        };
+        format!("{} {}", format_number(&val.num, &opts), get_unit_name(unit))
     } else {
         format_number(&val.num, opts)
     }
@@ -28,6 +49,12 @@ impl Display for Value {
     }
 }
 
+impl From<Number> for Value {
+    fn from(num: Number) -> Self {
+        Value { num, unit: None }
+    }
+}
+
 impl From<i64> for Value {
     fn from(item: i64) -> Self {
         Value {
@@ -1,4 +1,6 @@
 use crate::error::CalcError;
+use crate::number::Number;
+use crate::unit::UnitType;
 use crate::value::Value;
 use crate::{number_op, unit};
 
@@ -7,14 +9,19 @@ pub enum BinaryOp {
     Add,
     Sub,
     Mul,
+    Attach,
     Div,
+    IntDiv,
+    Mod,
     Pow,
+    Parallel,
     Conversion,
 }
 
 #[derive(Clone, Copy, PartialEq)]
 pub enum UnaryOp {
     Neg,
+    Fact,
 }
 
 impl BinaryOp {
@@ -23,8 +30,12 @@ impl BinaryOp {
             BinaryOp::Add => add(lhs, rhs),
             BinaryOp::Sub => sub(lhs, rhs),
             BinaryOp::Mul => mul(lhs, rhs),
+            BinaryOp::Attach => attach(lhs, rhs),
             BinaryOp::Div => div(lhs, rhs),
+            BinaryOp::IntDiv => int_div(lhs, rhs),
+            BinaryOp::Mod => modulo(lhs, rhs),
             BinaryOp::Pow => pow(lhs, rhs),
+            BinaryOp::Parallel => parallel(lhs, rhs),
             BinaryOp::Conversion => conversion(lhs, rhs),
         }
     }
@@ -34,8 +45,12 @@ impl BinaryOp {
             BinaryOp::Add => "+",
             BinaryOp::Sub => "-",
             BinaryOp::Mul => "*",
+            BinaryOp::Attach => " ",
             BinaryOp::Div => "/",
+            BinaryOp::IntDiv => "//",
+            BinaryOp::Mod => "%",
             BinaryOp::Pow => "^",
+            BinaryOp::Parallel => "||",
             BinaryOp::Conversion => "to",
         }
     }
@@ -45,12 +60,14 @@ impl UnaryOp {
     pub fn apply(self, val: Value) -> Result<Value, CalcError> {
         match self {
             UnaryOp::Neg => sub_unary(val),
+            UnaryOp::Fact => factorial(val),
         }
     }
 
     pub fn symbol(self) -> &'static str {
         match self {
             UnaryOp::Neg => "-",
+            UnaryOp::Fact => "!",
         }
     }
 }
@@ -71,35 +88,142 @@ pub fn pow(lhs: Value, rhs: Value) -> Result<Value, CalcError> {
     if rhs.unit.is_some() {
         return Err(CalcError::ExpByUnit);
     }
-    // Raising a united value to a power would produce a derived unit (e.g. m²),
-    // which we don't support yet — reuse OperateWithUnits until unit algebra lands.
-    if lhs.unit.is_some() {
+    let Some(lhs_unit) = lhs.unit else {
+        return Ok(Value {
+            num: number_op::pow(lhs.num, rhs.num)?,
+            unit: None,
+        });
+    };
+    // A united base carries its unit to the power (`(2 m)^2` → `4 m2`), which
+    // only makes sense for integer exponents.
+    let Number::Int(exp) = &rhs.num else {
         return Err(CalcError::OperateWithUnits);
-    }
+    };
+    let exp = i32::try_from(exp).map_err(|_| CalcError::OperateWithUnits)?;
     Ok(Value {
         num: number_op::pow(lhs.num, rhs.num)?,
-        unit: None,
+        unit: unit::pow(lhs_unit, exp),
     })
 }
 
 pub fn mul(lhs: Value, rhs: Value) -> Result<Value, CalcError> {
+    if is_absolute_temp(&lhs) || is_absolute_temp(&rhs) {
+        return Err(CalcError::AbsoluteTemperature);
+    }
+    let (factor, unit) = unit::combine(lhs.unit, rhs.unit, 1)?;
     Ok(Value {
-        num: number_op::mul(lhs.num, rhs.num),
-        unit: unit::single(lhs.unit, rhs.unit)?,
+        num: number_op::mul(number_op::mul(lhs.num, rhs.num)?, factor)?,
+        unit,
     })
 }
 
+// A bare unit after a value (`(2*3) eur`, `(-40) C`): a unitless value takes
+// the unit, which also works for absolute temperatures that `mul` rejects.
+// A value that already has a unit multiplies like `*`.
+pub fn attach(lhs: Value, rhs: Value) -> Result<Value, CalcError> {
+    if lhs.unit.is_none() {
+        return Ok(Value {
+            num: number_op::mul(lhs.num, rhs.num)?,
+            unit: rhs.unit,
+        });
+    }
+    mul(lhs, rhs)
+}
+
 pub fn div(lhs: Value, rhs: Value) -> Result<Value, CalcError> {
+    if is_absolute_temp(&lhs) || is_absolute_temp(&rhs) {
+        return Err(CalcError::AbsoluteTemperature);
+    }
+    let (factor, unit) = unit::combine(lhs.unit, rhs.unit, -1)?;
+    Ok(Value {
+        num: number_op::mul(number_op::div(lhs.num, rhs.num)?, factor)?,
+        unit,
+    })
+}
+
+// Resistors in parallel (or capacitors in series): `a*b / (a+b)`, so
+// `10 kΩ || 10 kΩ` is `5 kΩ`. The sum takes the lhs unit like `add` does.
+// A zero operand shorts the pair, so `0 || 0` is 0 rather than 0/0.
+pub fn parallel(lhs: Value, rhs: Value) -> Result<Value, CalcError> {
+    let sum = add(lhs.clone(), rhs.clone())?;
+    if number_op::is_zero(&lhs.num) || number_op::is_zero(&rhs.num) {
+        return Ok(Value {
+            num: Number::from(0),
+            unit: sum.unit,
+        });
+    }
+    div(mul(lhs, rhs)?, sum)
+}
+
+// Brings the divisor of `//` and `%` into the dividend's unit, so that
+// `100 min % 1 h` works in minutes. A unitless divisor leaves the unit alone
+// (`7 m % 2` is `1 m`); a united divisor needs a united dividend.
+fn divisor_in_lhs_unit(lhs: &Value, rhs: Value) -> Result<Number, CalcError> {
+    match (&lhs.unit, &rhs.unit) {
+        (_, None) => Ok(rhs.num),
+        (Some(lhs_unit), Some(rhs_unit)) if lhs_unit == rhs_unit => Ok(rhs.num),
+        (Some(lhs_unit), Some(rhs_unit)) => unit::convert(rhs.num, rhs_unit, lhs_unit),
+        (None, Some(_)) => Err(CalcError::OperateWithUnits),
+    }
+}
+
+pub fn int_div(lhs: Value, rhs: Value) -> Result<Value, CalcError> {
+    if is_absolute_temp(&lhs) || is_absolute_temp(&rhs) {
+        return Err(CalcError::AbsoluteTemperature);
+    }
+    // Like units cancel into a plain count: `7 ft // 2 ft` = 3.
+    let unit = if rhs.unit.is_some() {
+        None
+    } else {
+        lhs.unit.clone()
+    };
+    let rhs = divisor_in_lhs_unit(&lhs, rhs)?;
     Ok(Value {
-        num: number_op::div(lhs.num, rhs.num)?,
-        unit: unit::single(lhs.unit, rhs.unit)?,
+        num: number_op::int_div(lhs.num, rhs)?,
+        unit,
+    })
+}
+
+pub fn modulo(lhs: Value, rhs: Value) -> Result<Value, CalcError> {
+    if is_absolute_temp(&lhs) || is_absolute_temp(&rhs) {
+        return Err(CalcError::AbsoluteTemperature);
+    }
+    let rhs = divisor_in_lhs_unit(&lhs, rhs)?;
+    Ok(Value {
+        num: number_op::modulo(lhs.num, rhs)?,
+        unit: lhs.unit,
+    })
+}
+
+fn is_absolute_temp(val: &Value) -> bool {
+    matches!(&val.unit, Some(u) if unit::get_unit_type(u) == UnitType::Temperature)
+}
+
+// An absolute temperature is a point on its scale: adding an interval moves it
+// (`20 C + 5 ΔC` = `25 C`) and the interval is read in the point's scale.
+fn offset_temp(point: Value, interval: Value) -> Result<Value, CalcError> {
+    let point_unit = point.unit.expect("absolute temperature");
+    let Some(interval_unit) = interval.unit else {
+        return Err(CalcError::DifferentUnitTypes);
+    };
+    let scale = unit::temp_interval(&point_unit).expect("absolute temperature");
+    let offset = unit::convert(interval.num, &interval_unit, &scale)?;
+    Ok(Value {
+        num: number_op::add(point.num, offset)?,
+        unit: Some(point_unit),
     })
 }
 
 pub fn add(lhs: Value, rhs: Value) -> Result<Value, CalcError> {
+    match (is_absolute_temp(&lhs), is_absolute_temp(&rhs)) {
+        (true, true) => return Err(CalcError::AbsoluteTemperature),
+        (true, false) => return offset_temp(lhs, rhs),
+        (false, true) => return offset_temp(rhs, lhs),
+        (false, false) => {}
+    }
     if lhs.unit == rhs.unit {
         return Ok(Value {
-            num: number_op::add(lhs.num, rhs.num),
+            num: number_op::add(lhs.num, rhs.num)?,
             unit: lhs.unit,
         });
     }
@@ -107,7 +231,7 @@ pub fn add(lhs: Value, rhs: Value) -> Result<Value, CalcError> {
         if let Some(rhs_unit) = &rhs.unit {
             let converted = unit::convert(rhs.num, rhs_unit, lhs_unit)?;
             return Ok(Value {
-                num: number_op::add(lhs.num, converted),
+                num: number_op::add(lhs.num, converted)?,
                 unit: lhs.unit,
             });
         }
@@ -116,9 +240,24 @@ pub fn add(lhs: Value, rhs: Value) -> Result<Value, CalcError> {
 }
 
 pub fn sub(lhs: Value, rhs: Value) -> Result<Value, CalcError> {
+    match (is_absolute_temp(&lhs), is_absolute_temp(&rhs)) {
+        // The distance between two points is an interval: `30 C - 20 C` = `10 ΔC`.
+        (true, true) => {
+            let lhs_unit = lhs.unit.expect("absolute temperature");
+            let rhs_unit = rhs.unit.expect("absolute temperature");
+            let rhs = unit::convert(rhs.num, &rhs_unit, &lhs_unit)?;
+            return Ok(Value {
+                num: number_op::sub(lhs.num, rhs)?,
+                unit: unit::temp_interval(&lhs_unit),
+            });
+        }
+        (true, false) => return offset_temp(lhs, sub_unary(rhs)?),
+        (false, true) => return Err(CalcError::AbsoluteTemperature),
+        (false, false) => {}
+    }
     if lhs.unit == rhs.unit {
         return Ok(Value {
-            num: number_op::sub(lhs.num, rhs.num),
+            num: number_op::sub(lhs.num, rhs.num)?,
             unit: lhs.unit,
         });
     }
@@ -126,7 +265,7 @@ pub fn sub(lhs: Value, rhs: Value) -> Result<Value, CalcError> {
         if let Some(rhs_unit) = &rhs.unit {
             let converted = unit::convert(rhs.num, rhs_unit, lhs_unit)?;
             return Ok(Value {
-                num: number_op::sub(lhs.num, converted),
+                num: number_op::sub(lhs.num, converted)?,
                 unit: lhs.unit,
             });
         }
@@ -140,3 +279,13 @@ pub fn sub_unary(val: Value) -> Result<Value, CalcError> {
         unit: val.unit,
     })
 }
+
+pub fn factorial(val: Value) -> Result<Value, CalcError> {
+    if val.unit.is_some() {
+        return Err(CalcError::OperateWithUnits);
+    }
+    Ok(Value {
+        num: number_op::factorial(val.num)?,
+        unit: None,
+    })
+}
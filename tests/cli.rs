@@ -93,6 +93,17 @@ fn check(cases: &[(&str, &str)]) {
     }
 }
 
+/// Like `check`, but with `config_toml` as the config file.
+fn check_with_config(config_toml: &str, cases: &[(&str, &str)]) {
+    for (input, expected) in cases {
+        assert_eq!(
+            &eval_with_format_config(input, config_toml),
+            expected,
+            "input: {input:?}"
+        );
+    }
+}
+
 #[test]
 fn integer_arithmetic() {
     check(&[
@@ -135,16 +146,62 @@ fn rational_arithmetic_stays_exact() {
     ]);
 }
 
+#[test]
+fn big_numbers_stay_exact() {
+    check(&[
+        (
+            "2^200",
+            "1606938044258990275541962092341162602522202993782792835301376",
+        ),
+        ("30!", "265252859812191058636308480000000"),
+        (
+            "123456789012345678901234567890 * 3",
+            "370370367037037036703703703670",
+        ),
+        ("(1/3)^50 * 3^50", "1"),
+        // factorial binds tighter than ^ and unary minus
+        ("3!^2", "36"),
+        ("2^3!", "64"),
+        ("-3!", "-6"),
+        // rational exponents are exact for perfect powers
+        ("4^(1/2)", "2"),
+        ("(9/4)^(3/2)", "3.375"),
+        ("(-8)^(1/3)", "-2"),
+        ("2^(1/2)", "1.4142…"),
+        ("10^400 | sci", "1e400"),
+        ("2.5!", "Factorial needs a non-negative integer"),
+        ("(-1)!", "Factorial needs a non-negative integer"),
+    ]);
+}
+
 #[test]
 fn float_results() {
     check(&[
         ("2^0.5", "1.4142…"),
         ("-2^0.5", "-1.4142…"),
         // whole-valued floats render as plain integers
-        ("4^0.5", "2"),
+        ("2^0.5 * 0", "0"),
         ("0.0", "0"),
-        // IEEE 754 imprecision is visible via the … marker
-        ("0.1 + 0.2", "0.3000…"),
+    ]);
+}
+
+#[test]
+fn decimal_literals_stay_exact() {
+    check(&[
+        ("0.1 + 0.2", "0.3"),
+        ("0.1 * 3 - 0.3", "0"),
+        ("0.1 | rat", "1/10"),
+        ("1.5k", "1500"),
+        ("1.5k | rat", "1500"),
+        ("1e-3 * 1000", "1"),
+        ("2.5e2", "250"),
+        ("1e400 / 1e399", "10"),
+        // exponents past ±1000 are refused rather than expanded
+        ("1e1001", "Number out of range: 1e1001"),
+        ("2 * 1e-1001", "Number out of range: 1e-1001"),
+        ("1 / 0.3", "3.3333…"),
+        ("4^0.5", "2"),
+        ("8^(1/3) * 0.5", "1"),
     ]);
 }
 
@@ -174,6 +231,51 @@ fn length_conversions() {
     ]);
 }
 
+#[test]
+fn si_prefixes() {
+    check(&[
+        ("1 µm to nm", "1000 nm"),
+        ("1 um to µm", "1 µm"),
+        ("3 Mg to kg", "3000 kg"),
+        ("250 mg to g", "0.25 g"),
+        ("2 dl + 50 ml", "2.5 dl"),
+        ("1 kl to l", "1000 l"),
+        ("5 mL to l", "0.005 l"),
+        ("1 dL to ml", "100 ml"),
+        ("2 kL to l", "2000 l"),
+        ("1 mK to K", "0.001 K"),
+        ("5 mK + 2 ΔK", "2005 mK"),
+        ("1 ms to s", "0.001 s"),
+        ("1 Gs to Ts", "0.001 Ts"),
+        ("5 cm3 to ml", "5 ml"),
+        ("1 km2 to m2", "1000000 m2"),
+        ("sqrt(4 km2)", "2 km"),
+        // a suffix only applies at the end of the number
+        ("3km", "3 km"),
+        ("5mm to m", "0.005 m"),
+        ("1.5kg to g", "1500 g"),
+    ]);
+}
+
+#[test]
+fn extended_length_units() {
+    check(&[
+        ("1 ly to AU", "63241.0771… AU"),
+        ("5 nmi to km", "9.26 km"),
+        ("1 pc to ly", "3.2616… ly"),
+        // large results upgrade to scientific notation
+        ("1 AU to km", "1.4960…e8 km"),
+        ("1 Mpc to ly", "3.2616…e6 ly"),
+        // whole results too, whether exact (ly) or float (pc, via π)
+        ("1 ly to m", "9.4607…e15 m"),
+        ("1000 ly to nm", "9.4607…e27 nm"),
+        ("1 pc to m", "3.0857…e16 m"),
+        ("1 Å to nm", "0.1 nm"),
+        ("1 furlong to m", "201.168 m"),
+        ("1 mi to ch", "80 ch"),
+    ]);
+}
+
 #[test]
 fn mass_volume_time_conversions() {
     check(&[
@@ -185,6 +287,209 @@ fn mass_volume_time_conversions() {
     ]);
 }
 
+#[test]
+fn speed_conversions() {
+    check(&[
+        ("60 mph to km/h", "96.5606… km/h"),
+        ("10 knots to m/s", "5.1444… m/s"),
+        ("88 ft/s to mph", "60 mph"),
+        ("1 nmi to m", "1852 m"),
+        // a distance over a time is a speed
+        ("100 mi / 2 h", "50 mph"),
+        ("100 km / 2 h", "50 km/h"),
+        ("10 kn * 2 h", "20 nmi"),
+    ]);
+}
+
+#[test]
+fn data_units() {
+    check(&[
+        ("4.7 GB to GiB", "4.3772… GiB"),
+        ("1 KiB to B", "1024 B"),
+        ("1 byte to bits", "8 bit"),
+        ("2 TiB to GiB", "2048 GiB"),
+        ("100Mbit to MB", "12.5 MB"),
+        // a size over a rate is a transfer time
+        ("8 GiB / 100 Mbit/s to min", "11.4532… min"),
+        ("3 kbit/s * 1 min", "180 kbit"),
+    ]);
+}
+
+#[test]
+fn energy_and_power() {
+    check(&[
+        ("2.5 kW * 3 h to kWh", "7.5 kWh"),
+        ("100 kWh * 60 HUF/kWh", "6000 HUF"),
+        ("1 kWh to MJ", "3.6 MJ"),
+        ("1 kcal to J", "4184 J"),
+        ("1 BTU to J", "1055.0559… J"),
+        ("1 hp to W", "745.6999… W"),
+        ("3 kJ / 2 s to W", "1500 W"),
+        ("60 W * 8 h to kWh", "0.48 kWh"),
+    ]);
+}
+
+#[test]
+fn pressure_units() {
+    check(&[
+        ("32 psi to bar", "2.2063… bar"),
+        ("1 atm to hPa", "1013.25 hPa"),
+        ("1 bar to kPa", "100 kPa"),
+        ("1013 mbar to atm", "0.9998… atm"),
+        ("760 mmHg to kPa", "101.3250… kPa"),
+    ]);
+}
+
+#[test]
+fn fuel_economy() {
+    check(&[
+        ("6.5 L/100km to mpg", "36.1869… mpg"),
+        ("6.5 L/100km to mpg | rat", "112903/3120 mpg"),
+        ("6.5 L/100km to mpg_imp", "43.4586… mpg_imp"),
+        ("20 km/L to L/100km", "5 L/100km"),
+        ("5 L/100km to km/l", "20 km/l"),
+        ("30 mpg to km/l", "12.7543… km/l"),
+        ("300 mi / 10 gal", "30 mpg"),
+        ("0 L/100km to mpg", "Division by zero"),
+    ]);
+}
+
+#[test]
+fn cooking_volumes() {
+    check(&[
+        ("3 tsp to tbsp", "1 tbsp"),
+        ("16 floz to cup", "2 cup"),
+        ("1 fl oz to ml", "29.5735… ml"),
+        ("1 mcup to ml", "250 ml"),
+        ("1 impgal to l", "4.5461… l"),
+        ("1 usgal to impgal", "0.8327… impgal"),
+        ("1 imppt to impfloz", "20 impfloz"),
+    ]);
+}
+
+#[test]
+fn cooking_volumes_follow_region() {
+    let imperial = "[units]\nregion = \"imperial\"\n";
+    check_with_config(
+        imperial,
+        &[
+            ("1 gallon to l", "4.5461… l"),
+            ("2 cup to ml", "500 ml"),
+            ("1 tbsp to ml", "15 ml"),
+            ("1 pint to fl oz", "20 fl oz"),
+            ("1 usgal to gallon", "0.8327… gallon"),
+            ("1 gallon to usgal", "1.2009… usgal"),
+            ("1 ton to lb", "2240 lb"),
+        ],
+    );
+}
+
+#[test]
+fn typography_units() {
+    check(&[
+        ("1 in to pts", "72 pts"),
+        ("1 pica to pts", "12 pts"),
+        ("12 pts to px", "16 px"),
+        ("2 em to px", "32 px"),
+        ("96 px to mm", "25.4 mm"),
+        ("1.5 em to pts", "18 pts"),
+        ("1 pts to mm", "0.3528… mm"),
+        // `pt` is still the pint
+        ("1 pt to ml", "473.1765… ml"),
+        ("2 pt to pint", "2 pint"),
+        ("1 pt to pts", "Different unit types"),
+    ]);
+}
+
+#[test]
+fn screen_units_follow_config() {
+    let config = "[units]\ndpi = 72\nem = 12\n";
+    check_with_config(
+        config,
+        &[
+            ("12 pts to px", "12 px"),
+            ("1 em to pts", "12 pts"),
+            ("1 in to px", "72 px"),
+        ],
+    );
+}
+
+#[test]
+fn extended_mass_units() {
+    check(&[
+        ("11 st 4 lb to kg", "71.6676… kg"),
+        ("5 lb 3 oz to g", "2353.0104… g"),
+        ("3 t + 500 kg", "3.5 t"),
+        ("1 tonne to kg", "1000 kg"),
+        ("1 ton to lb", "2000 lb"),
+        ("1 longton to lb", "2240 lb"),
+        ("1 ozt to g", "31.1035… g"),
+        ("5 ct to mg", "1000 mg"),
+        ("7000 gr to lb", "1 lb"),
+    ]);
+}
+
+#[test]
+fn extended_time_units() {
+    check(&[
+        ("3 weeks to h", "504 h"),
+        ("250 ms * 40 to s", "10 s"),
+        ("2 d 3 h 15 min 10 s to s", "184510 s"),
+        ("1 year to d", "365.25 d"),
+        ("1 month to d", "30.4375 d"),
+        ("1 fortnight to d", "14 d"),
+        ("2 hours 30 minutes to min", "150 min"),
+    ]);
+}
+
+#[test]
+fn electrical_units() {
+    check(&[
+        ("10 kΩ || 10 kΩ", "5 kΩ"),
+        ("10k || 10k", "5000"),
+        ("4.7 µF * 10 kΩ to ms", "47 ms"),
+        ("12 V / 2 kΩ to mA", "6 mA"),
+        ("5 V * 2 A to W", "10 W"),
+        ("2 farad to µF", "2000000 µF"),
+        ("3 mA * 2 s to mC", "6 mC"),
+        ("1 kohm || 1 kohm || 2 kohm", "0.4 kΩ"),
+        // a zero operand shorts the pair instead of dividing 0 by 0
+        ("0 || 0", "0"),
+        ("0 Ω || 10 kΩ", "0 Ω"),
+        ("10 || 0", "0"),
+        // Bare `F` and `C` stay temperatures.
+        ("20 C to F", "68 F"),
+        ("1 dF to dR", "1 ΔR"),
+        ("10 kΩ || 5 kΩ | fixed 2", "3.33… kΩ"),
+    ]);
+}
+
+#[test]
+fn frequency_units() {
+    check(&[
+        ("50 Hz to ms", "20 ms"),
+        ("1 kHz to Hz", "1000 Hz"),
+        ("2 GHz to ns", "0.5 ns"),
+        ("3000 rpm to Hz", "50 Hz"),
+        ("120 bpm to s", "0.5 s"),
+        ("0.5 s to bpm", "120 bpm"),
+        ("1 / (2 ms) to Hz", "500 Hz"),
+    ]);
+}
+
+#[test]
+fn land_area_units() {
+    check(&[
+        ("1200 négyszögöl to m2 | rat", "674372167923/156250000 m2"),
+        ("2 acre to ha | rat", "158080329/195312500 ha"),
+        ("1 hold to négyszögöl", "1600 négyszögöl"),
+        ("1 km2 to ha", "100 ha"),
+        ("1 ha to are", "100 are"),
+        ("1 mi2 to acre", "640 acre"),
+        ("1 acre to sqrod", "160 sqrod"),
+    ]);
+}
+
 #[test]
 fn temperature_conversions() {
     check(&[
@@ -195,6 +500,47 @@ fn temperature_conversions() {
         // parenthesise so the unit attaches before conversion (not after negation)
         ("(-40) c to f", "-40 F"),
         ("(-40) f to c", "-40 C"),
+        ("0 C to K", "273.15 K"),
+        ("32 F to K", "273.15 K"),
+        ("491.67 R to F", "32 F"),
+        ("20 C to C", "20 C"),
+    ]);
+}
+
+#[test]
+fn temperature_intervals() {
+    check(&[
+        ("20 C + 5 ΔC", "25 C"),
+        ("5 dC + 20 C", "25 C"),
+        ("20 C - 9 ΔF", "15 C"),
+        ("30 C - 20 C", "10 ΔC"),
+        ("80 F - 20 C", "12 ΔF"),
+        ("10 ΔC to ΔF", "18 ΔF"),
+        (
+            "20 C + 30 C",
+            "Absolute temperatures can only be offset by an interval or subtracted",
+        ),
+        (
+            "10 ΔC - 20 C",
+            "Absolute temperatures can only be offset by an interval or subtracted",
+        ),
+        ("10 ΔC to C", "Different unit types"),
+        ("20 C + 5", "Different unit types"),
+        (
+            "20 C / 2",
+            "Absolute temperatures can only be offset by an interval or subtracted",
+        ),
+        (
+            "2 * 20 C",
+            "Absolute temperatures can only be offset by an interval or subtracted",
+        ),
+        (
+            "20 C % 7",
+            "Absolute temperatures can only be offset by an interval or subtracted",
+        ),
+        ("20 ΔC / 2", "10 ΔC"),
+        ("100 J / 20 ΔK", "5 J/ΔK"),
+        ("(2*3) C", "6 C"),
     ]);
 }
 
@@ -233,20 +579,67 @@ fn error_messages() {
         ("1/0", "Division by zero"),
         ("5 m + 3 kg", "Different unit types"),
         ("5 m to kg", "Different unit types"),
-        // multiplying/dividing two united values is rejected (no derived units)
-        ("3 m * 2 m", "Cannot operate with units"),
-        ("100 km / 2 h", "Cannot operate with units"),
+        ("100 km / 2 h to kg", "Different unit types"),
         ("2 ^ 3 m", "Exponent cannot have a unit"),
-        // raising a united value to a power would be a derived unit — not yet
-        ("(2 m)^2", "Cannot operate with units"),
+        // a united base only takes integer powers
+        ("(2 m)^0.5", "Cannot operate with units"),
+    ]);
+}
+
+#[test]
+fn derived_units() {
+    check(&[
+        ("3 m * 2 m", "6 m2"),
+        ("100 km / 2 h", "50 km/h"),
+        ("(2 m)^2", "4 m2"),
+        ("(2 m)^3 to l", "8000 l"),
+        // powers of a length fold back into the named area/volume units
+        ("m * m to ft2", "10.7639… ft2"),
+        ("1 m^2 to ft2", "10.7639… ft2"),
+        // parts of the same type merge into the left unit
+        ("2 km * 500 m", "1 km2"),
+        ("10 ft / 2 in", "60"),
+        ("60 km/h to m/s", "16.6667… m/s"),
+        ("100 km/h * 2 h", "200 km"),
+        ("3 m / s^2", "3 m/s^2"),
+    ]);
+}
+
+#[test]
+fn modulo_and_floor_division() {
+    check(&[
+        ("10 % 3", "1"),
+        ("10 // 3", "3"),
+        // floor semantics: the remainder takes the divisor's sign
+        ("(-7) % 3", "2"),
+        ("7 % (-3)", "-2"),
+        ("(-7) // 2", "-4"),
+        // unary minus binds tighter than `%` and `//`
+        ("-7 % 3", "2"),
+        ("-7 // 2", "-4"),
+        ("10 % -3", "-2"),
+        ("-2^2", "-4"),
+        ("7.5 % 2", "1.5"),
+        ("(1/2) % (1/3)", "0.1667…"),
+        ("2^0.5 % 1", "0.4142…"),
+        ("10 // 3 * 3 + 10 % 3", "10"),
+        ("5 % 0", "Division by zero"),
+        ("5 // 0", "Division by zero"),
+        // the divisor is converted into the dividend's unit
+        ("100 min % 1 h", "40 min"),
+        ("1 h // 25 min", "2"),
+        ("7 ft // 2 ft", "3"),
+        ("7 m % 2", "1 m"),
+        ("7 m // 2", "3 m"),
+        ("5 % 2 m", "Cannot operate with units"),
+        ("5 m % 2 kg", "Different unit types"),
     ]);
 }
 
 #[test]
 fn unparseable_input_produces_no_output() {
-    // `%` is tokenized but has no grammar rule, so the expression fails to parse
-    // and nothing is printed. Documents current behaviour.
-    assert_eq!(eval("10 % 3"), "");
+    // a dangling operator fails to parse and nothing is printed
+    assert_eq!(eval("10 %"), "");
     // mass isn't a compound group, so `5 kg 10 g` doesn't parse
     assert_eq!(eval("5 kg 10 g"), "");
     // incomplete expression (trailing operator)
@@ -321,6 +714,33 @@ fn config_malformed_calc_config_errors() {
     );
 }
 
+#[test]
+fn custom_units_from_config() {
+    let config = "[units.custom]\npallet = \"1.2 m2\"\nsprint = \"2 week\"\npoint = \"\"\n";
+    check_with_config(
+        config,
+        &[
+            ("5 pallet to m2", "6 m2"),
+            ("1 sprint to d", "14 d"),
+            ("3 sprint + 1 week", "3.5 sprint"),
+            ("40 point / 1 sprint", "40 point/sprint"),
+            ("40 point / 1 sprint to point/week", "20 point/wk"),
+            ("pallet", "1 pallet"),
+            ("5 point + 3 m", "Different unit types"),
+        ],
+    );
+}
+
+#[test]
+fn custom_unit_clashing_with_builtin_errors() {
+    let dir = tempfile::tempdir().expect("create temp dir");
+    let conf = dir.path().join("conf.toml");
+    std::fs::write(&conf, "[units.custom]\npt = \"1 m\"\n").expect("write config");
+    let (ok, _, err) = eval_with_env("2+2", &[("CALC_CONFIG", conf.to_str().unwrap())], &[]);
+    assert!(!ok, "a clashing custom unit should cause non-zero exit");
+    assert!(err.contains("not a free identifier"), "got: {err:?}");
+}
+
 #[test]
 fn config_first_run_bootstrap_creates_file() {
     let home = tempfile::tempdir().expect("create temp home dir");
@@ -452,6 +872,39 @@ fn format_operator_rational() {
     assert_eq!(eval("1/2 + 1/2 | rat"), "1");
 }
 
+#[test]
+fn format_exact_expansion() {
+    check(&[
+        // rationals expand by long division, not through f64
+        (
+            "1/3 | fixed 40",
+            "0.3333333333333333333333333333333333333333…",
+        ),
+        ("1/7 | sci 20", "1.42857142857142857143…e-1"),
+        ("2^200 / 3 | sci 20", "5.35646014752996758514…e59"),
+        ("99999/100000 | sci 2", "1.00…e0"),
+    ]);
+}
+
+#[test]
+fn format_operator_repeating() {
+    check(&[
+        ("1/3 | rep", "0.(3)"),
+        ("1/6 | repeating", "0.1(6)"),
+        ("-22/7 | rep", "-3.(142857)"),
+        // terminating decimals and integers have no period
+        ("1/4 | rep", "0.25"),
+        ("5 | rep", "5"),
+        ("10 km / 3 h | rep", "3.(3) km/h"),
+        // periods beyond the cap fall back to the rounded expansion
+        ("1/1009 | rep", "0.0010…"),
+    ]);
+    assert_eq!(
+        eval_with_format_config("1/12", "[format]\nrepr = \"repeating\""),
+        "0.08(3)"
+    );
+}
+
 #[test]
 fn format_operator_fixed() {
     assert_eq!(eval("1/3 | fixed 6"), "0.333333\u{2026}");
@@ -518,6 +971,168 @@ fn ans_keyword() {
     assert_eq!(out, "0.8333\u{2026}\n5/6");
 }
 
+#[test]
+fn variables() {
+    // one-shot: `;` separates statements; assignments print their value
+    check(&[
+        ("price = 20; price * 3", "20\n60"),
+        ("speed = 10 km/h; speed * 2 h", "10 km/h\n20 km"),
+        ("x = 3; x^2 + ans", "3\n12"),
+        ("y * 2", "Unknown variable: y"),
+        // words that start with a unit name are still identifiers
+        ("sprint = 4; sprint + 1", "4\n5"),
+        ("x2 = 5 m; x2 to cm", "5 m\n500 cm"),
+        // unit names and keywords win over variables
+        ("m = 5", "Cannot assign to m: it is a unit or keyword"),
+        ("ans = 2", "Cannot assign to ans: it is a unit or keyword"),
+        ("5ft11in to cm", "180.34 cm"),
+    ]);
+
+    // REPL: variables persist between lines and can be listed and removed
+    let out = eval_repl("rate = 392.5 HUF\nw = 2\n/vars\n/unset w\n/vars\nw\n/unset w\n");
+    assert_eq!(
+        out,
+        "392.5 HUF\n2\nrate = 392.5 HUF\nw = 2\nrate = 392.5 HUF\nUnknown variable: w\nunknown variable or function \"w\""
+    );
+}
+
+#[test]
+fn builtin_functions() {
+    check(&[
+        // exact where possible
+        ("sqrt(9/4) | rat", "3/2"),
+        ("abs(-1/3) | rat", "1/3"),
+        ("cbrt(-8)", "-2"),
+        ("log10(0.001)", "-3"),
+        ("log(8, 2)", "3"),
+        ("floor(-7/2)", "-4"),
+        ("ceil(7/2)", "4"),
+        ("round(-2.5)", "-3"),
+        ("cos(0)", "1"),
+        // otherwise float
+        ("sqrt(2)", "1.4142…"),
+        ("ln(2)", "0.6931…"),
+        ("exp(1)", "2.7183…"),
+        ("asin(0.3)", "17.4576… deg"),
+        // units
+        ("abs(-3 m)", "3 m"),
+        ("max(1 ft, 20 cm)", "1 '"),
+        ("min(5 min, 2 h)", "5 min"),
+        ("sqrt(9 m2)", "3 m"),
+        ("round(1.26 kg)", "1 kg"),
+        ("ln(2 m)", "Cannot operate with units"),
+        ("sqrt(2 m)", "Cannot operate with units"),
+        // a named unit without a root of its own goes through SI units
+        ("sqrt(1 acre)", "63.6149… m"),
+        ("sqrt(1 ha)", "100 m"),
+        ("cbrt(1 l)", "0.1 m"),
+        ("sqrt(1 kWh)", "Cannot operate with units"),
+        ("max(1 m, 2)", "Different unit types"),
+        // outside the domain
+        ("sqrt(-4)", "Argument outside the domain of sqrt"),
+        ("asin(2)", "Argument outside the domain of asin"),
+        ("acos(-3/2)", "Argument outside the domain of acos"),
+        ("ln(0)", "Argument outside the domain of ln"),
+        ("log(8, 0)", "Argument outside the domain of log"),
+        ("log(1, 1)", "Argument outside the domain of log"),
+        ("sqrt(1, 2)", "sqrt takes 1 argument(s), got 2"),
+        ("sqrt(x) = x", "Cannot redefine built-in function sqrt"),
+    ]);
+}
+
+#[test]
+fn angles() {
+    check(&[
+        ("1 turn to deg", "360 deg"),
+        ("400 grad to turn", "1 turn"),
+        ("1 deg to arcsec", "3600 arcsec"),
+        ("1 rad to deg", "57.2958… deg"),
+        ("12 deg 30 arcmin", "12.5 deg"),
+        // trig honours the angle unit, exactly at multiples of 30° / 45°
+        ("sin(30 deg)", "0.5"),
+        ("cos(0.5 turn)", "-1"),
+        ("tan(45°)", "1"),
+        ("tan(90 deg)", "Division by zero"),
+        ("sin(45 deg)", "0.7071…"),
+        ("sin(1 rad)", "0.8415…"),
+        ("sin(1)", "0.8415…"),
+        ("sin(2 m)", "Cannot operate with units"),
+        // inverse trig returns degrees
+        ("asin(1/2)", "30 deg"),
+        ("atan(1) to rad", "0.7854… rad"),
+        // degree-minute-second input and output
+        ("12°34'56\" to deg | rat", "2831/225 deg"),
+        ("12° 34' 56\" | dms", "12°34'56\""),
+        ("-45.5 deg | dms", "-45°30'0\""),
+        // seconds that round to 60 carry into minutes and degrees
+        ("0.99999999999 deg | dms", "1°0'0\""),
+        ("1 deg - 1e-12 deg | dms", "1°0'0\""),
+        ("1.49999999999 deg | dms", "1°30'0\""),
+        ("1 rad | dms", "57°17'44.8062…\""),
+        ("5 | dms", "5"),
+        // feet and inches are unaffected
+        ("5'11\" to cm", "180.34 cm"),
+    ]);
+}
+
+#[test]
+fn functions() {
+    check(&[
+        ("area(w, h) = w * h; area(3 m, 2 m)", "6 m2"),
+        ("vat(x) = x * 1.27; vat(100 EUR)", "127 EUR"),
+        ("speed(d, t) = d / t; speed(10 km, 2 h)", "5 km/h"),
+        // functions can call other functions
+        (
+            "sq(x) = x^2; hyp(a, b) = (sq(a) + sq(b))^0.5; hyp(3, 4)",
+            "5",
+        ),
+        // nested calls share one parsed body per function instead of
+        // re-expanding it, so this stays fast (a7 doubles its argument 2^6 times)
+        (
+            "a1(x) = x + x; a2(x) = a1(a1(x)); a3(x) = a2(a2(x)); a4(x) = a3(a3(x)); \
+             a5(x) = a4(a4(x)); a6(x) = a5(a5(x)); a7(x) = a6(a6(x)); a7(1)",
+            "18446744073709551616",
+        ),
+        // variables in the body are looked up at the call
+        ("r = 2; dbl(x) = x * r; r = 3; dbl(2)", "2\n3\n6"),
+        (
+            "area(w, h) = w * h; area(2)",
+            "area takes 2 argument(s), got 1",
+        ),
+        ("nope(2)", "Unknown function: nope"),
+        ("loop(x) = loop(x)", "Function loop would call itself"),
+        // `f`, `g` and `s` lex as units, so they can't name a function
+        (
+            "f(x) = x * 2; f(3)",
+            "Cannot assign to F: it is a unit or keyword",
+        ),
+        ("g(x) = x", "Cannot assign to g: it is a unit or keyword"),
+        ("s(x, y) = x", "Cannot assign to s: it is a unit or keyword"),
+        ("a(x) = b(x); b(x) = a(x)", "Function b would call itself"),
+        ("dup(a, a) = a", "Duplicate parameter a"),
+        ("dup(h, x, h) = x", "Duplicate parameter h"),
+        (
+            "twice(h) = 2 h",
+            "Parameter h is used as a unit in the body",
+        ),
+    ]);
+
+    // REPL: a rejected signature or body doesn't swallow the following lines
+    let out = eval_repl("dup(a, a) = a\n1 + 1\ntwice(h) = h h\n2 + 2\n");
+    assert_eq!(
+        out,
+        "Duplicate parameter a\n2\nParameter h is used as a unit in the body\n4"
+    );
+
+    // REPL: definitions persist and can be listed and removed
+    let out =
+        eval_repl("area(w, h) = w * h\nvat(x) = x * 1.27\n/funcs\n/unset vat\n/funcs\nvat(1)\n");
+    assert_eq!(
+        out,
+        "area(w, h) = w * h\nvat(x) = x * 1.27\narea(w, h) = w * h\nUnknown function: vat"
+    );
+}
+
 #[test]
 fn currency_static_provider() {
     const CONFIG: &str = "[currency]\nprovider = \"static\"\n\n[currency.static]\n\"EUR/USD\" = 1.08\n\"USD/HUF\" = 360.0\n";
@@ -610,6 +1225,13 @@ fn config_set_session_affects_subsequent_expression() {
     assert!(out.contains("0.333333"), "expected 6 dp result: {out:?}");
 }
 
+#[test]
+fn config_set_units_dpi_affects_px() {
+    let out = eval_repl("/config units.dpi 300\n1 in to px\n");
+    assert!(out.contains("units.dpi = 300"), "set output: {out:?}");
+    assert!(out.contains("300 px"), "expected 300 px: {out:?}");
+}
+
 #[test]
 fn config_set_invalid_value_prints_error() {
     let out = eval_repl("/config format.float.precision notanumber\n");